@@ -0,0 +1,98 @@
+use std::{fmt, time::{Duration, Instant}};
+
+use bevy_math::Vec3;
+use robot_arm::ik::FabrikChain;
+
+const REPEATING_PATTERN_LEN: usize = 8;
+
+struct SeededRng(u64);
+
+impl SeededRng {
+    fn new(seed: u64) -> Self {
+        Self(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
+    
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+    
+    fn next_signed(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+}
+
+pub struct BenchSummary {
+    pub targets: usize,
+    pub total_time: Duration,
+    pub average_iterations: f32,
+}
+
+impl fmt::Display for BenchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "| targets | total time (ms) | avg time (us) | avg iterations |")?;
+        writeln!(f, "|---------|-----------------|---------------|----------------|")?;
+        write!(
+            f,
+            "| {:>7} | {:>15.3} | {:>13.3} | {:>14.2} |",
+            self.targets,
+            self.total_time.as_secs_f64() * 1e3,
+            self.total_time.as_secs_f64() * 1e6 / self.targets.max(1) as f64,
+            self.average_iterations,
+        )
+    }
+}
+
+pub fn generate_targets(seed: u64, count: usize, radius: f32) -> Vec<Vec3> {
+    let mut rng = SeededRng::new(seed);
+    let mut targets = Vec::with_capacity(count);
+    while targets.len() < count {
+        let point = Vec3::new(rng.next_signed(), rng.next_signed(), rng.next_signed());
+        if point.length_squared() <= 1.0 {
+            targets.push(point * radius);
+        }
+    }
+    targets
+}
+
+fn time_batch(chain: &mut FabrikChain, targets: &[Vec3], iterations: usize) -> BenchSummary {
+    let start = Instant::now();
+    let total_iterations = chain.solve_batch(targets, iterations);
+    
+    BenchSummary {
+        targets: targets.len(),
+        total_time: start.elapsed(),
+        average_iterations: total_iterations as f32 / targets.len().max(1) as f32,
+    }
+}
+
+pub fn run(chain: &FabrikChain, seed: u64, count: usize, iterations: usize) -> BenchSummary {
+    let mut chain = chain.clone();
+    let reach = chain.lengths.iter().sum();
+    let targets = generate_targets(seed, count, reach);
+    time_batch(&mut chain, &targets, iterations)
+}
+
+pub fn run_repeating(chain: &FabrikChain, seed: u64, count: usize, iterations: usize, warm_start: bool) -> BenchSummary {
+    let mut chain = chain.clone();
+    chain.warm_start = warm_start;
+    let reach = chain.lengths.iter().sum();
+    let pattern = generate_targets(seed, REPEATING_PATTERN_LEN, reach);
+    let targets: Vec<Vec3> = pattern.iter().copied().cycle().take(count).collect();
+    time_batch(&mut chain, &targets, iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn fixed_seed_generates_a_deterministic_sequence() {
+        let first = generate_targets(42, 16, 2.0);
+        assert_eq!(first, generate_targets(42, 16, 2.0));
+        assert_ne!(first, generate_targets(43, 16, 2.0));
+        assert!(first.iter().all(|target| target.length() <= 2.0));
+    }
+}
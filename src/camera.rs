@@ -0,0 +1,88 @@
+use bevy::{
+    input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit},
+    prelude::*,
+};
+use bevy_egui::EguiContexts;
+
+const HOME_EYE: Vec3 = Vec3::new(0.0, 6.0, 7.0);
+const ORBIT_SENSITIVITY: f32 = 0.005;
+const PAN_SENSITIVITY: f32 = 0.0015;
+const ZOOM_SENSITIVITY: f32 = 0.1;
+const PIXELS_PER_LINE: f32 = 40.0;
+const MAX_PITCH: f32 = 1.5;
+const MIN_RADIUS: f32 = 1.0;
+const MAX_RADIUS: f32 = 50.0;
+
+#[derive(Message, Default)]
+pub struct ResetCamera;
+
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct OrbitCamera {
+    pub focus: Vec3,
+    pub radius: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self::looking_from(HOME_EYE, Vec3::ZERO)
+    }
+}
+
+impl OrbitCamera {
+    pub fn looking_from(eye: Vec3, focus: Vec3) -> Self {
+        let offset = eye - focus;
+        let radius = offset.length().max(MIN_RADIUS);
+        Self {
+            focus,
+            radius,
+            yaw: offset.x.atan2(offset.z),
+            pitch: (offset.y / radius).clamp(-1.0, 1.0).asin(),
+        }
+    }
+    
+    pub fn transform(&self) -> Transform {
+        let rotation = Quat::from_euler(EulerRot::YXZ, self.yaw, -self.pitch, 0.0);
+        Transform::from_translation(self.focus + rotation * Vec3::Z * self.radius).looking_at(self.focus, Vec3::Y)
+    }
+}
+
+pub fn camera_controls(
+    mut context: EguiContexts,
+    mouse_buttons: Res<ButtonInput<MouseButton>>,
+    mouse_motion: Res<AccumulatedMouseMotion>,
+    mouse_scroll: Res<AccumulatedMouseScroll>,
+    mut query_camera: Query<(&mut OrbitCamera, &mut Transform)>,
+    mut ev_reset_camera: MessageReader<ResetCamera>
+) {
+    let Ok((mut orbit, mut transform)) = query_camera.single_mut() else { return; };
+    if ev_reset_camera.read().last().is_some() {
+        *orbit = OrbitCamera::default();
+    }
+    
+    let egui_has_pointer = context
+        .ctx_mut()
+        .is_ok_and(|ctx| ctx.wants_pointer_input() || ctx.is_pointer_over_area());
+    if !egui_has_pointer {
+        let motion = mouse_motion.delta;
+        if mouse_buttons.pressed(MouseButton::Right) {
+            orbit.yaw -= motion.x * ORBIT_SENSITIVITY;
+            orbit.pitch = (orbit.pitch + motion.y * ORBIT_SENSITIVITY).clamp(-MAX_PITCH, MAX_PITCH);
+        }
+        if mouse_buttons.pressed(MouseButton::Middle) {
+            let pan = transform.right() * -motion.x + transform.up() * motion.y;
+            orbit.focus += pan * PAN_SENSITIVITY * orbit.radius;
+        }
+        let scroll = match mouse_scroll.unit {
+            MouseScrollUnit::Line => mouse_scroll.delta.y,
+            MouseScrollUnit::Pixel => mouse_scroll.delta.y / PIXELS_PER_LINE,
+        };
+        orbit.radius = (orbit.radius * (1.0 - scroll * ZOOM_SENSITIVITY)).clamp(MIN_RADIUS, MAX_RADIUS);
+    }
+    
+    let target = orbit.transform();
+    if *transform != target {
+        *transform = target;
+    }
+}
@@ -0,0 +1,43 @@
+use bevy::prelude::*;
+#[cfg(feature = "serde")]
+use robot_arm::ik::FabrikChain;
+
+#[cfg(feature = "serde")]
+use crate::{
+    ControlBall, InnerBall, LimbAssets, LimbData, Segment, SyncTransform, UiState,
+    despawn_limb_entities, spawn_limb_entities,
+};
+
+pub const CONFIG_FILE: &str = "arm.json";
+
+#[derive(Message, Default)]
+pub struct LoadConfig;
+
+#[cfg(feature = "serde")]
+pub fn load_config(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chain: Query<&mut LimbData>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    mut ui_state: ResMut<UiState>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    let new_chain = match FabrikChain::load_json(CONFIG_FILE) {
+        Ok(new_chain) => new_chain,
+        Err(err) => {
+            warn!("Could not load {CONFIG_FILE}: {err}");
+            ui_state.config_error = Some(format!("Could not load {CONFIG_FILE}: {err}"));
+            return;
+        }
+    };
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    
+    despawn_limb_entities(&mut commands, &query_limb_entities);
+    spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &new_chain);
+    ui_state.lock_ground = new_chain.lock_ground;
+    ui_state.lock_fantasy_ground = new_chain.limb.as_ref().is_some_and(|limb| limb.lock_ground);
+    ui_state.config_error = None;
+    chain.0 = new_chain;
+    ev_sync_transforms.write_default();
+}
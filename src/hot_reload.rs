@@ -0,0 +1,145 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, mpsc::{Receiver, channel}},
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+use robot_arm::ik::FabrikChain;
+
+use crate::{
+    ControlBall, InnerBall, LimbAssets, LimbData, RespawnLimb, Segment, SyncTransform,
+    despawn_limb_entities, spawn_limb_entities,
+    templates::rebuild_chain,
+};
+
+const CHAIN_FILE: &str = "chain.points";
+const DEBOUNCE: Duration = Duration::from_millis(250);
+
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        let (tx, rx) = channel();
+        let mut watcher = notify::recommended_watcher(tx).expect("Could not create file watcher");
+        watcher
+            .watch(Path::new("."), RecursiveMode::NonRecursive)
+            .expect("Could not watch working directory");
+        
+        app.insert_resource(ChainWatcher {
+            _watcher: watcher,
+            events: Mutex::new(rx),
+            path: PathBuf::from(CHAIN_FILE),
+            pending_since: None,
+        })
+        .add_systems(Update, reload_chain_file.in_set(RespawnLimb));
+    }
+}
+
+#[derive(Resource)]
+struct ChainWatcher {
+    _watcher: RecommendedWatcher,
+    events: Mutex<Receiver<notify::Result<Event>>>,
+    path: PathBuf,
+    pending_since: Option<Instant>,
+}
+
+pub fn parse_points(contents: &str) -> Result<Vec<Vec3>, String> {
+    let joints = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let coords = line
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|coord| !coord.is_empty())
+                .map(|coord| coord.parse::<f32>().map_err(|err| format!("{line:?}: {err}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            match coords[..] {
+                [x, y, z] => Ok(Vec3::new(x, y, z)),
+                _ => Err(format!("{line:?}: expected 3 coordinates")),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    
+    if joints.len() < 2 {
+        return Err(format!("expected at least 2 joints, found {}", joints.len()));
+    }
+    Ok(joints)
+}
+
+pub fn reload_chain(current: &FabrikChain, contents: &str) -> Result<FabrikChain, String> {
+    Ok(rebuild_chain(current, parse_points(contents)?))
+}
+
+fn reload_chain_file(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut watcher: ResMut<ChainWatcher>,
+    mut query_chain: Query<&mut LimbData>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    let changed = watcher
+        .events
+        .lock()
+        .unwrap()
+        .try_iter()
+        .filter_map(Result::ok)
+        .any(|event| {
+            (event.kind.is_modify() || event.kind.is_create())
+                && event.paths.iter().any(|path| path.ends_with(CHAIN_FILE))
+        });
+    if changed {
+        watcher.pending_since = Some(Instant::now());
+    }
+    
+    match watcher.pending_since {
+        Some(since) if since.elapsed() >= DEBOUNCE => watcher.pending_since = None,
+        _ => return,
+    }
+    
+    let contents = match std::fs::read_to_string(&watcher.path) {
+        Ok(contents) => contents,
+        Err(err) => {
+            warn!("Could not read {}: {err}", watcher.path.display());
+            return;
+        }
+    };
+    
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    match reload_chain(&chain.0, &contents) {
+        Ok(new_chain) => {
+            despawn_limb_entities(&mut commands, &query_limb_entities);
+            spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &new_chain);
+            chain.0 = new_chain;
+            ev_sync_transforms.write_default();
+        }
+        Err(err) => warn!("Could not reload {}: {err}", watcher.path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use robot_arm::ik::MotionHueristics;
+    
+    #[test]
+    fn successive_contents_reload_in_turn() {
+        let mut current = FabrikChain::new(vec![Vec3::ZERO, Vec3::X], MotionHueristics::default());
+        current.lock_ground = false;
+        
+        let first = reload_chain(&current, "0 0 0\n1 0 0\n2 0 0\n").unwrap();
+        assert_eq!(first.joints, vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0]);
+        assert!(!first.lock_ground);
+        
+        let second = reload_chain(&first, "# edited\n0, 0, 0\n0, 1, 0\n").unwrap();
+        assert_eq!(second.joints, vec![Vec3::ZERO, Vec3::Y]);
+        assert_eq!(second.lengths, vec![1.0]);
+        assert!(!second.lock_ground);
+        
+        assert!(reload_chain(&second, "0 0 0\n1 0\n").is_err());
+    }
+}
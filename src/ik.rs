@@ -1,9 +1,30 @@
-use std::time::SystemTime;
+use std::{collections::VecDeque, fmt::{self, Write}, io, path::Path, sync::{Arc, Mutex}, time::SystemTime};
 
+#[cfg(feature = "bevy")]
 use bevy::transform::components::Transform;
-use bevy_math::{Mat3, NormedVectorSpace, Quat, Vec3, VectorSpace};
+use bevy_math::{IVec3, Mat3, NormedVectorSpace, Quat, Vec2, Vec3, VectorSpace};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use tracing::{debug, trace, warn};
 
-#[derive(Default)]
+#[cfg(not(feature = "bevy"))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+fn segment_lengths(joints: &[Vec3]) -> Vec<f32> {
+    joints
+        .windows(2)
+        .map(|pair| pair[1].distance(pair[0]))
+        .collect()
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, strum::EnumIter)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum PoseDiscrepancy {
     #[default]
     WithinTolerance,
@@ -12,72 +33,344 @@ pub enum PoseDiscrepancy {
     EnvironmentalCompensation,
 }
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, strum::EnumIter)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum SolverKind {
+    #[default]
+    Fabrik,
+    Ccd,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum KinematicsMode {
     #[default]
     InverseKinematics,
     ForwardKinematics
 }
 
+const DEGENERATE_EPSILON: f32 = 1e-4;
+const CONVERGENCE_TOLERANCE: f32 = 1e-3;
+const GRAVITY: f32 = 9.81;
+const MAX_COLLISION_ROUNDS: usize = 10;
+const FIT_ITERATIONS: usize = 10;
+const FLIP_BIAS: f32 = 0.25;
+const WARM_START_QUANTUM: f32 = 0.05;
+const WARM_START_CAPACITY: usize = 32;
+const DISTANCE_STIFFNESS: f32 = 0.5;
+const SEVERE_DIVERGENCE_THRESHOLD: f32 = 2.0;
+const MIN_SEGMENT_LENGTH: f32 = 0.05;
+const MIN_FRAME_DELTA: f32 = 1e-4;
+const PARALLEL_EPSILON: f32 = 1e-6;
+
+#[derive(Debug, Clone, Default)]
+pub struct SolveReport {
+    pub iterations: usize,
+    pub refinement_iterations: usize,
+    pub final_error: f32,
+    pub converged: bool,
+    pub reachable: bool,
+    pub max_joint_delta: f32,
+    pub error_history: Vec<f32>,
+}
+
+fn closest_points(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.length_squared(), d2.length_squared(), d2.dot(r));
+    
+    let (s, t) = if a <= f32::EPSILON && e <= f32::EPSILON {
+        (0.0, 0.0)
+    } else if a <= f32::EPSILON {
+        (0.0, (f / e).clamp(0.0, 1.0))
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            ((-c / a).clamp(0.0, 1.0), 0.0)
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            let mut s = if denom > f32::EPSILON { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+            let mut t = (b * s + f) / e;
+            if t < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            }
+            (s, t)
+        }
+    };
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+fn segment_distance(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> f32 {
+    let (c1, c2) = closest_points(p1, q1, p2, q2);
+    c1.distance(c2)
+}
+
+fn bend_offset(joints: &[Vec3], index: usize) -> Vec3 {
+    let (a, b, c) = (joints[index - 1], joints[index], joints[index + 1]);
+    let ac = c - a;
+    let t = if ac.length_squared() > 0.0 { (b - a).dot(ac) / ac.length_squared() } else { 0.0 };
+    b - (a + ac * t)
+}
+
+fn segment_rotation(a: Vec3, b: Vec3, world_axis: Vec3) -> Quat {
+    let ab_vector = (b - a).normalize_or(Vec3::Y);
+    
+    let perp_vector = [world_axis, Vec3::X]
+        .into_iter()
+        .map(|axis| ab_vector.cross(axis))
+        .find(|perp| perp.length_squared() > PARALLEL_EPSILON)
+        .map_or_else(|| ab_vector.any_orthonormal_vector(), Vec3::normalize);
+    let perp_vector2 = ab_vector.cross(perp_vector).normalize();
+    Quat::from_mat3(&Mat3::from_cols(ab_vector, perp_vector, perp_vector2)) * Quat::from_rotation_z(90f32.to_radians())
+}
+
+pub fn smooth_trajectory(poses: &mut [Vec<Vec3>], window: usize) {
+    if poses.len() < 3 || window < 2 {
+        return;
+    }
+    
+    let original = poses.to_vec();
+    let half = window / 2;
+    for i in 1..poses.len() - 1 {
+        let frames = &original[i.saturating_sub(half)..(i + half + 1).min(original.len())];
+        for (j, joint) in poses[i].iter_mut().enumerate() {
+            let (sum, count) = frames
+                .iter()
+                .filter_map(|frame| frame.get(j))
+                .fold((Vec3::ZERO, 0.0), |(sum, count), pos| (sum + *pos, count + 1.0));
+            *joint = sum / count;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum ReachPass {
+    #[default]
+    Untouched,
+    Forward,
+    Backward,
+}
+
+#[derive(Debug, Clone)]
+pub enum TargetCommand {
+    Set(usize, Vec3),
+    Clear(usize),
+    ClearAll,
+    Anchor(usize, Vec3),
+}
+
+#[derive(Clone)]
+pub struct IterationHook(Arc<Mutex<dyn FnMut(&FabrikChain, usize) + Send>>);
+
+impl IterationHook {
+    pub fn new(hook: impl FnMut(&FabrikChain, usize) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new(hook)))
+    }
+}
+
+impl fmt::Debug for IterationHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("IterationHook")
+    }
+}
+
+pub trait Solver: Send + Sync {
+    fn solve(&self, chain: &mut FabrikChain, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize;
+}
+
+pub struct Fabrik;
+
+impl Solver for Fabrik {
+    fn solve(&self, chain: &mut FabrikChain, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize {
+        chain.fabrik_iterations(goals, prev_joints, iterations)
+    }
+}
+
+pub struct Ccd;
+
+impl Solver for Ccd {
+    fn solve(&self, chain: &mut FabrikChain, goals: &[(usize, Vec3)], _prev_joints: &[Vec3], iterations: usize) -> usize {
+        chain.ccd_iterations(goals, iterations)
+    }
+}
+
+impl Solver for SolverKind {
+    fn solve(&self, chain: &mut FabrikChain, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize {
+        match self {
+            SolverKind::Fabrik => Fabrik.solve(chain, goals, prev_joints, iterations),
+            SolverKind::Ccd => Ccd.solve(chain, goals, prev_joints, iterations),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct CustomSolver(Arc<dyn Solver>);
+
+impl CustomSolver {
+    pub fn new(solver: impl Solver + 'static) -> Self {
+        Self(Arc::new(solver))
+    }
+}
+
+impl fmt::Debug for CustomSolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomSolver")
+    }
+}
+
 type AnchorPoints = Vec<(usize, Vec3, Quat)>;
 type ParentRanking = Vec<(usize, i32, i32)>;
+type WarmStartKey = Vec<(usize, IVec3)>;
 
 #[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct MotionHueristics {
     pub anchor_points: AnchorPoints,
     pub parent_ranking: ParentRanking,
+    pub ground_height: Option<f32>,
+    pub obstacle_planes: Vec<(Vec3, f32)>,
 }
 
 impl MotionHueristics {
     fn new(anchor_points: AnchorPoints, parent_ranking: ParentRanking) -> Self {
-        Self { anchor_points,parent_ranking }
+        Self { anchor_points,parent_ranking, ..Default::default() }
+    }
+    
+    pub fn add_obstacle_plane(&mut self, normal: Vec3, point: Vec3) {
+        let normal = normal.normalize_or(Vec3::Y);
+        self.obstacle_planes.push((normal, normal.dot(point)));
     }
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct FabrikChain {
     pub joints: Vec<Vec3>,
+    pub joint_labels: Vec<Option<String>>,
+    pub frozen: Vec<bool>,
+    pub hinge_axes: Vec<Option<Vec3>>,
+    pub distance_constraints: Vec<(usize, usize, f32)>,
+    pub angle_limits: Vec<(f32, f32)>,
     pub lengths: Vec<f32>,
+    pub segment_masses: Vec<f32>,
     pub segment_transforms: Vec<Transform>,
     pub angles: Vec<f32>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub bend_axes: Vec<Vec3>,
+    pub rest_angles: Vec<f32>,
     pub prev_angles: Vec<f32>,
     pub angular_velocities: Vec<f32>,
+    pub prev_angular_velocities: Vec<f32>,
+    pub angular_accelerations: Vec<f32>,
+    pub segment_velocities: Vec<f32>,
     pub targets: Vec<(usize, Vec3)>,
+    pub prev_targets: Vec<(usize, Vec3)>,
+    pub prediction_time: f32,
+    pub stretch_tolerance: Option<f32>,
+    pub length_budget: Option<f32>,
+    pub fk_ik_blend: f32,
+    pub tool_offset: Vec3,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tool_roll: f32,
+    pub tip_orientation: Option<Quat>,
+    pub pole_target: Option<Vec3>,
+    pub up_axis: Vec3,
+    pub epsilon: f32,
+    pub tolerance: f32,
+    pub segment_directions: Vec<Vec3>,
+    pub pass_touched: Vec<ReachPass>,
+    pub last_pass: ReachPass,
+    pub avoid_self_collision: bool,
+    pub collision_radius: f32,
+    pub prevent_flips: bool,
+    pub equalize_velocity: bool,
+    pub solver: SolverKind,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub custom_solver: Option<CustomSolver>,
+    pub warm_start: bool,
+    pub warm_start_cache: VecDeque<(WarmStartKey, Vec<Vec3>)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_iteration: Option<IterationHook>,
+    pub debug_solve: bool,
     pub motion_heuristics: MotionHueristics,
+    #[cfg_attr(feature = "serde", serde(skip, default = "SystemTime::now"))]
     pub prev_time: SystemTime,
     pub lock_ground: bool,
+    compensating: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    iteration_errors: Vec<f32>,
     pub limb: Option<Box<Self>>,
     initial_state: Option<Box<Self>>
 }
 
 impl FabrikChain {
     pub fn new(joints: Vec<Vec3>, motion_heuristics: MotionHueristics) -> Self {
-        let mut lengths = Vec::new();
-        for i in 1..joints.len() {
-            let length = joints[i].distance(joints[i-1]);
-            lengths.push(length);
-        }
+        assert!(joints.len() >= 2, "FabrikChain needs at least 2 joints, got {}", joints.len());
+        let lengths = segment_lengths(&joints);
         let new_self = Self {
+            segment_masses: vec![1.0; lengths.len()],
             joints,
+            joint_labels: Vec::new(),
+            frozen: Vec::new(),
+            hinge_axes: Vec::new(),
+            distance_constraints: Vec::new(),
+            angle_limits: Vec::new(),
             lengths,
             prev_angles: Vec::new(),
             angles: Vec::new(),
+            bend_axes: Vec::new(),
+            rest_angles: Vec::new(),
             angular_velocities: Vec::new(),
+            prev_angular_velocities: Vec::new(),
+            angular_accelerations: Vec::new(),
+            segment_velocities: Vec::new(),
             prev_time: SystemTime::now(),
             initial_state: None,
             segment_transforms: Vec::new(),
             motion_heuristics,
             targets: Vec::new(),
+            prev_targets: Vec::new(),
+            prediction_time: 0.0,
+            stretch_tolerance: None,
+            length_budget: None,
+            fk_ik_blend: 1.0,
+            tool_offset: Vec3::ZERO,
+            tool_roll: 0.0,
+            tip_orientation: None,
+            pole_target: None,
+            up_axis: Vec3::Y,
+            epsilon: DEGENERATE_EPSILON,
+            tolerance: CONVERGENCE_TOLERANCE,
+            segment_directions: Vec::new(),
+            pass_touched: Vec::new(),
+            last_pass: ReachPass::Untouched,
+            avoid_self_collision: false,
+            collision_radius: 0.15,
+            prevent_flips: false,
+            equalize_velocity: false,
+            solver: SolverKind::default(),
+            custom_solver: None,
+            warm_start: false,
+            warm_start_cache: VecDeque::new(),
+            on_iteration: None,
+            debug_solve: false,
             lock_ground: true,
+            compensating: false,
+            iteration_errors: Vec::new(),
             limb: None,
         };
         
         let mut final_self = Self {
             limb: Some(Box::new(new_self.clone())),
-            initial_state: Some(Box::new(new_self.clone())),
             ..new_self
         };
         final_self.recalculate_segments();
+        final_self.set_home();
         
         final_self
     }
@@ -91,34 +384,408 @@ impl FabrikChain {
         self
     }
     
-    pub fn get_ee(&self) -> &Vec3 {
-        self.joints.last().expect("Joints should not be empty")
+    pub fn get_ee(&self) -> Vec3 {
+        let last = *self.joints.last().expect("Joints should not be empty");
+        self.tool_rotation().map_or(last, |rotation| last + rotation * self.tool_offset)
+    }
+    
+    // Local +Y points along the last segment, rolled about it by tool_roll.
+    pub fn get_ee_orientation(&self) -> Quat {
+        let tip = self.joints.len() - 1;
+        segment_rotation(self.joints[tip], self.joints[tip - 1], self.up_axis) * Quat::from_rotation_y(self.tool_roll)
+    }
+    
+    pub fn get_ee_pose(&self) -> Transform {
+        Transform { translation: self.get_ee(), rotation: self.get_ee_orientation(), scale: Vec3::ONE }
+    }
+    
+    fn tool_rotation(&self) -> Option<Quat> {
+        if self.tool_offset == Vec3::ZERO || self.joints.len() < 2 {
+            return None;
+        }
+        Some(self.get_ee_orientation())
+    }
+    
+    fn effector_position(&self, index: usize) -> Vec3 {
+        if index == self.joints.len() - 1 { self.get_ee() } else { self.joints[index] }
+    }
+    
+    fn place_goal(&mut self, index: usize, pos: Vec3) {
+        let offset = match self.tool_rotation() {
+            Some(rotation) if index == self.joints.len() - 1 => rotation * self.tool_offset,
+            _ => Vec3::ZERO,
+        };
+        self.joints[index] = pos - offset;
+    }
+    
+    pub fn joint_label(&self, index: usize) -> Option<&str> {
+        self.joint_labels.get(index).and_then(Option::as_deref)
+    }
+    
+    pub fn is_frozen(&self, index: usize) -> bool {
+        self.frozen.get(index).copied().unwrap_or(false)
+    }
+    
+    pub fn set_frozen(&mut self, index: usize, frozen: bool) {
+        if self.frozen.len() < self.joints.len() {
+            self.frozen.resize(self.joints.len(), false);
+        }
+        self.frozen[index] = frozen;
+    }
+    
+    pub fn hinge_axis(&self, index: usize) -> Option<Vec3> {
+        self.hinge_axes.get(index).copied().flatten()
+    }
+    
+    pub fn set_hinge(&mut self, index: usize, axis: Option<Vec3>) {
+        if self.hinge_axes.len() < self.joints.len() {
+            self.hinge_axes.resize(self.joints.len(), None);
+        }
+        self.hinge_axes[index] = axis.and_then(Vec3::try_normalize);
+    }
+    
+    pub fn angle_limit(&self, joint: usize) -> (f32, f32) {
+        joint
+            .checked_sub(1)
+            .and_then(|i| self.angle_limits.get(i))
+            .copied()
+            .unwrap_or((0.0, std::f32::consts::PI))
+    }
+    
+    pub fn set_angle_limit(&mut self, joint: usize, limit: (f32, f32)) {
+        let interior = self.joints.len().saturating_sub(2);
+        if self.angle_limits.len() < interior {
+            self.angle_limits.resize(interior, (0.0, std::f32::consts::PI));
+        }
+        self.angle_limits[joint - 1] = limit;
+    }
+    
+    pub fn is_anchored(&self, index: usize) -> bool {
+        self.motion_heuristics
+            .anchor_points
+            .iter()
+            .any(|(anchor_index, _, _)| *anchor_index == index)
+    }
+    
+    pub fn is_limit_pinned(&self, joint: usize) -> bool {
+        let (min, max) = self.angle_limit(joint);
+        (max - min).abs() <= f32::EPSILON
+    }
+    
+    fn anchor_position(&self, index: usize) -> Option<Vec3> {
+        self.motion_heuristics
+            .anchor_points
+            .iter()
+            .find(|(anchor_index, _, _)| *anchor_index == index)
+            .map(|(_, pos, _)| *pos)
+    }
+    
+    pub fn effective_dof(&self) -> usize {
+        (1..self.joints.len().saturating_sub(1))
+            .filter(|&i| !self.is_frozen(i) && !self.is_anchored(i) && !self.is_limit_pinned(i))
+            .count()
+    }
+    
+    pub fn aabb(&self) -> (Vec3, Vec3) {
+        self.joints.iter().fold(
+            (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY)),
+            |(min, max), joint| (min.min(*joint), max.max(*joint))
+        )
+    }
+    
+    pub fn bounding_sphere(&self) -> (Vec3, f32) {
+        let (min, max) = self.aabb();
+        let center = (min + max) / 2.0;
+        let radius = self
+            .joints
+            .iter()
+            .map(|joint| joint.distance(center))
+            .fold(0.0, f32::max);
+        (center, radius)
+    }
+    
+    fn segment_mass(&self, index: usize) -> f32 {
+        self.segment_masses.get(index).copied().unwrap_or(1.0)
+    }
+    
+    fn segment_midpoint(&self, index: usize) -> Vec3 {
+        (self.joints[index] + self.joints[index + 1]) / 2.0
+    }
+    
+    // Saturates at the full segment count when the target is out of reach.
+    pub fn min_segments_for(&self, target: Vec3) -> usize {
+        let distance = target.distance(self.joints[0]);
+        let mut reach = 0.0;
+        for (i, length) in self.lengths.iter().enumerate() {
+            if reach >= distance {
+                return i;
+            }
+            reach += length;
+        }
+        self.lengths.len()
+    }
+    
+    pub fn as_capsules(&self, radius: f32) -> Vec<(Vec3, Vec3, f32)> {
+        self.joints
+            .windows(2)
+            .map(|pair| (pair[0], pair[1], radius))
+            .collect()
+    }
+    
+    pub fn self_intersections(&self, radius: f32) -> Vec<(usize, usize)> {
+        let capsules = self.as_capsules(radius);
+        let mut intersections = Vec::new();
+        for i in 0..capsules.len() {
+            for j in (i + 2)..capsules.len() {
+                let ((p1, q1, r1), (p2, q2, r2)) = (capsules[i], capsules[j]);
+                if segment_distance(p1, q1, p2, q2) < r1 + r2 {
+                    intersections.push((i, j));
+                }
+            }
+        }
+        intersections
+    }
+    
+    fn enforce_lengths_from_root(&mut self) {
+        for i in 0..self.lengths.len() {
+            let direction = self.direction_or(self.joints[i+1] - self.joints[i], self.prior_direction(i));
+            self.joints[i+1] = self.joints[i] + direction * self.lengths[i];
+        }
+    }
+    
+    pub fn resolve_self_collisions(&mut self) -> bool {
+        let radius = self.collision_radius;
+        for _ in 0..MAX_COLLISION_ROUNDS {
+            let intersections = self.self_intersections(radius);
+            if intersections.is_empty() {
+                return true;
+            }
+            for (i, j) in intersections {
+                let (c1, c2) = closest_points(self.joints[i], self.joints[i+1], self.joints[j], self.joints[j+1]);
+                let normal = (c1 - c2).try_normalize().unwrap_or(Vec3::Y);
+                let push = normal * ((radius * 2.0 - c1.distance(c2)).min(radius) / 2.0);
+                for k in [i, i + 1] {
+                    if k != 0 || !self.lock_ground {
+                        self.joints[k] += push;
+                    }
+                }
+                for k in [j, j + 1] {
+                    self.joints[k] -= push;
+                }
+            }
+            self.enforce_lengths_from_root();
+        }
+        self.self_intersections(radius).is_empty()
+    }
+    
+    pub fn center_of_mass(&self) -> Vec3 {
+        let (weighted, total) = (0..self.lengths.len()).fold((Vec3::ZERO, 0.0), |(weighted, total), i| {
+            let mass = self.segment_mass(i);
+            (weighted + self.segment_midpoint(i) * mass, total + mass)
+        });
+        if total > 0.0 { weighted / total } else { self.joints[0] }
+    }
+    
+    pub fn static_torques(&self, tip_force: Vec3) -> Vec<f32> {
+        let ee = self.get_ee();
+        self.joints
+            .iter()
+            .map(|joint| (ee - *joint).cross(tip_force).length())
+            .collect()
+    }
+    
+    pub fn static_torques_with_self_weight(&self, tip_force: Vec3) -> Vec<f32> {
+        let ee = self.get_ee();
+        self.joints
+            .iter()
+            .enumerate()
+            .map(|(i, joint)| {
+                let self_weight = (i..self.lengths.len())
+                    .map(|k| (self.segment_midpoint(k) - *joint).cross(Vec3::NEG_Y * GRAVITY * self.segment_mass(k)))
+                    .sum::<Vec3>();
+                ((ee - *joint).cross(tip_force) + self_weight).length()
+            })
+            .collect()
     }
     
     pub fn recalculate_segments(&mut self) {
         let frame_delta_time = self
             .prev_time
             .elapsed()
-            .expect("Could not get elapsed time");
+            .map_or(0.0, |elapsed| elapsed.as_secs_f32());
         self.prev_time = SystemTime::now();
+        let per_second = |delta: f32| if frame_delta_time > MIN_FRAME_DELTA { delta / frame_delta_time } else { 0.0 };
         
-        self.angular_velocities.clear();
-        for i in 0..self.prev_angles.len() {
-            self.angular_velocities.push((self.angles[i] - self.prev_angles[i]) / (frame_delta_time.as_micros() as f32));
+        self.prev_angular_velocities = std::mem::take(&mut self.angular_velocities);
+        for (angle, prev_angle) in self.angles.iter().zip(self.prev_angles.iter()) {
+            self.angular_velocities.push(per_second(angle - prev_angle));
+        }
+        self.angular_accelerations.clear();
+        if self.prev_angular_velocities.len() == self.angular_velocities.len() {
+            for (velocity, prev_velocity) in self.angular_velocities.iter().zip(self.prev_angular_velocities.iter()) {
+                self.angular_accelerations.push(per_second(velocity - prev_velocity));
+            }
         }
+        let prev_midpoints: Vec<Vec3> = self
+            .segment_transforms
+            .iter()
+            .map(|transform| transform.translation)
+            .collect();
         self.segment_transforms.clear();
+        let prev_directions = std::mem::take(&mut self.segment_directions);
         for i in 1..self.joints.len() {
             let (a, b) = (self.joints[i], self.joints[i-1]);
-            let ab_vector = (b - a).normalize();
+            let fallback = prev_directions.get(i - 1).copied().unwrap_or(Vec3::X);
+            self.segment_directions.push(self.direction_or(a - b, fallback));
+            let quat = segment_rotation(a, b, self.up_axis);
             
-            let world_axis = Vec3::new(0.0, 1.0, 0.0);
-            let perp_vector = ab_vector.cross(world_axis).normalize();
-            let perp_vector2 = ab_vector.cross(perp_vector).normalize();
-            let quat = Quat::from_mat3(&Mat3::from_cols(ab_vector, perp_vector, perp_vector2)) * Quat::from_rotation_z(90f32.to_radians());
+            let radius_scale = self.segment_mass(i - 1).sqrt();
+            let stretch = self
+                .lengths
+                .get(i - 1)
+                .map_or(1.0, |length| a.distance(b) / length);
+            let scale = Vec3::new(radius_scale, stretch, radius_scale);
             
-            self.segment_transforms.push(Transform { translation: (a + b) / 2.0, rotation: quat, scale: Vec3::ONE });
+            self.segment_transforms.push(Transform { translation: (a + b) / 2.0, rotation: quat, scale });
+        }
+        
+        self.segment_velocities.clear();
+        if prev_midpoints.len() == self.segment_transforms.len() {
+            for (prev, transform) in prev_midpoints.iter().zip(self.segment_transforms.iter()) {
+                self.segment_velocities.push(per_second(transform.translation.distance(*prev)));
+            }
+        }
+        
+        if self.segment_transforms.len() != self.lengths.len() {
+            warn!(
+                "Segment count {} does not match length count {}, rebuilding lengths from joints",
+                self.segment_transforms.len(),
+                self.lengths.len()
+            );
+            self.lengths = segment_lengths(&self.joints);
+        }
+    }
+    
+    fn sanitize_pose(&mut self) {
+        for joint in self.joints.iter_mut() {
+            *joint = Vec3::from_array(joint.to_array().map(|c| if c.is_finite() { c } else { 0.0 }));
+        }
+        while self.joints.len() < 2 {
+            let last = self.joints.last().copied().unwrap_or(Vec3::ZERO);
+            self.joints.push(last + Vec3::X);
+        }
+        
+        let joint_count = self.joints.len();
+        self.lengths = segment_lengths(&self.joints);
+        self.segment_masses.resize(joint_count - 1, 1.0);
+        for mass in self.segment_masses.iter_mut() {
+            if !mass.is_finite() || *mass <= 0.0 {
+                *mass = 1.0;
+            }
+        }
+        self.joint_labels.truncate(joint_count);
+        self.frozen.truncate(joint_count);
+        self.hinge_axes.truncate(joint_count);
+        self.angle_limits.truncate(joint_count - 2);
+        self.distance_constraints
+            .retain(|(a, b, distance)| *a < joint_count && *b < joint_count && distance.is_finite());
+        self.targets.retain(|(index, pos)| *index < joint_count && pos.is_finite());
+        self.tip_orientation = self.tip_orientation.filter(|rotation| rotation.is_finite());
+        self.prev_targets.clear();
+        self.motion_heuristics
+            .anchor_points
+            .retain(|(index, pos, _)| *index < joint_count && pos.is_finite());
+        self.motion_heuristics
+            .obstacle_planes
+            .retain(|(normal, offset)| normal.is_normalized() && offset.is_finite());
+        
+        self.recalculate_angles();
+        self.prev_angles.clear();
+        if self.rest_angles.len() != self.angles.len() {
+            self.rest_angles.clear();
+        }
+        self.segment_transforms.clear();
+        self.recalculate_segments();
+    }
+    
+    pub fn sanitize(&mut self) {
+        self.sanitize_pose();
+        
+        let joint_count = self.joints.len();
+        match self.limb.as_mut() {
+            Some(limb) if limb.joints.len() == joint_count => {
+                limb.limb = None;
+                limb.sanitize_pose();
+            }
+            _ => {
+                let mut limb = self.clone();
+                limb.limb = None;
+                limb.initial_state = None;
+                self.limb = Some(Box::new(limb));
+            }
+        }
+        if self.initial_state.as_ref().is_none_or(|home| home.joints.len() != joint_count) {
+            self.set_home();
+        }
+    }
+    
+    pub fn set_segment_length(&mut self, segment: usize, length: f32) {
+        let length = if length.is_finite() { length.max(MIN_SEGMENT_LENGTH) } else { self.lengths[segment] };
+        let direction = self.direction_or(self.joints[segment + 1] - self.joints[segment], self.prior_direction(segment));
+        let correction = self.joints[segment] + direction * length - self.joints[segment + 1];
+        for joint in self.joints[segment + 1..].iter_mut() {
+            *joint += correction;
+        }
+        self.lengths[segment] = length;
+        self.recalculate_segments();
+    }
+    
+    pub fn push_joint(&mut self, length: f32) {
+        let tip = self.joints.len() - 1;
+        let direction = self.direction_or(self.joints[tip] - self.joints[tip - 1], self.prior_direction(tip - 1));
+        let pos = self.joints[tip] + direction * length;
+        self.joints.push(pos);
+        self.sanitize_pose();
+        for chain in [self.limb.as_mut(), self.initial_state.as_mut()].into_iter().flatten() {
+            chain.push_joint(length);
+        }
+    }
+    
+    pub fn pop_joint(&mut self) {
+        if self.joints.len() <= 2 {
+            return;
+        }
+        self.joints.pop();
+        self.sanitize_pose();
+        for chain in [self.limb.as_mut(), self.initial_state.as_mut()].into_iter().flatten() {
+            chain.pop_joint();
+        }
+    }
+    
+    pub fn clear_constraints(&mut self) {
+        self.frozen.clear();
+        self.hinge_axes.clear();
+        self.distance_constraints.clear();
+        self.angle_limits.clear();
+        self.motion_heuristics.anchor_points.clear();
+        self.motion_heuristics.ground_height = None;
+        self.motion_heuristics.obstacle_planes.clear();
+        self.stretch_tolerance = None;
+        self.length_budget = None;
+        self.avoid_self_collision = false;
+        self.prevent_flips = false;
+        self.pole_target = None;
+        self.lock_ground = true;
+        if let Some(limb) = self.limb.as_mut() {
+            limb.clear_constraints();
         }
-        assert_eq!(self.segment_transforms.len(), self.lengths.len());
+    }
+    
+    pub fn set_home(&mut self) {
+        let mut home = self.clone();
+        home.initial_state = None;
+        home.warm_start_cache.clear();
+        self.initial_state = Some(Box::new(home));
     }
     
     pub fn reset(&mut self) {
@@ -133,70 +800,2211 @@ impl FabrikChain {
         self.recalculate_segments();
     }
     
-    pub fn fwd_reach(&mut self) {
-        for i in (0..self.joints.len() - 1).rev() {
-            let (a, b) = (self.joints[i], self.joints[i-1]);
-            let direction = (a - b).normalize();
-            self.joints[i] = b + direction * self.lengths[i];
+    pub fn set_joint(&mut self, index: usize, pos: Vec3, resolve_iterations: Option<usize>) -> Option<SolveReport> {
+        self.joints[index] = pos;
+        if index > 0 {
+            self.lengths[index - 1] = self.joints[index].distance(self.joints[index - 1]);
+        }
+        if index + 1 < self.joints.len() {
+            self.lengths[index] = self.joints[index + 1].distance(self.joints[index]);
+        }
+        
+        match resolve_iterations {
+            Some(iterations) => Some(self.solve(iterations, PoseDiscrepancy::default(), &mut KinematicsMode::default())),
+            None => {
+                self.recalculate_segments();
+                None
+            }
         }
     }
     
-    pub fn bwd_reach(&mut self) {
-        for i in 0..self.joints.len() - 1 {
-            let (a, b) = (self.joints[i], self.joints[i-1]);
-            let direction = (b - a).normalize();
-            self.joints[i+1] = a + direction * self.lengths[i];
+    pub fn apply_target_commands(&mut self, commands: impl IntoIterator<Item = TargetCommand>) {
+        for command in commands {
+            match command {
+                TargetCommand::Set(index, pos) => {
+                    match self.targets.iter_mut().find(|(target_index, _)| *target_index == index) {
+                        Some((_, target)) => *target = pos,
+                        None => self.targets.push((index, pos)),
+                    }
+                }
+                TargetCommand::Clear(index) => {
+                    self.targets.retain(|(target_index, _)| *target_index != index);
+                }
+                TargetCommand::ClearAll => {
+                    self.targets.clear();
+                }
+                TargetCommand::Anchor(index, pos) => {
+                    let anchors = &mut self.motion_heuristics.anchor_points;
+                    anchors.retain(|(anchor_index, _, _)| *anchor_index != index);
+                    anchors.push((index, pos, Quat::IDENTITY));
+                }
+            }
         }
     }
     
-    pub fn recalculate_angles(&mut self) {
-        std::mem::swap(&mut self.angles, &mut self.prev_angles);
+    pub fn closest_point(&self, segment: usize, point: Vec3) -> Vec3 {
+        let (a, b) = (self.joints[segment], self.joints[segment + 1]);
+        let ab = b - a;
+        let t = ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0);
+        a + ab * t
+    }
+    
+    pub fn insert_joint(&mut self, segment: usize, pos: Vec3) {
+        let (a, b) = (self.joints[segment], self.joints[segment + 1]);
+        self.joints.insert(segment + 1, pos);
+        if segment < self.joint_labels.len() {
+            self.joint_labels.insert(segment + 1, None);
+        }
+        if segment < self.frozen.len() {
+            self.frozen.insert(segment + 1, false);
+        }
+        if segment < self.hinge_axes.len() {
+            self.hinge_axes.insert(segment + 1, None);
+        }
+        if segment <= self.angle_limits.len() && !self.angle_limits.is_empty() {
+            self.angle_limits.insert(segment, (0.0, std::f32::consts::PI));
+        }
+        self.lengths.splice(segment..=segment, [a.distance(pos), pos.distance(b)]);
+        if segment < self.segment_masses.len() {
+            self.segment_masses.insert(segment, self.segment_masses[segment]);
+        }
+        for (index, _) in self.targets.iter_mut() {
+            if *index > segment {
+                *index += 1;
+            }
+        }
+        for (a, b, _) in self.distance_constraints.iter_mut() {
+            for index in [a, b] {
+                if *index > segment {
+                    *index += 1;
+                }
+            }
+        }
+        self.prev_targets.clear();
         self.angles.clear();
-        self.angles.push(std::f32::consts::PI);
-        for i in 2..self.joints.len() {
-            let (a, b, c) = (self.joints[i-2], self.joints[i-1], self.joints[i]);
-            let angle = (a-b).angle_between(c-b);
-            self.angles.push(angle);
+        self.bend_axes.clear();
+        self.prev_angles.clear();
+        self.rest_angles.clear();
+        
+        if let Some(limb) = self.limb.as_mut() {
+            let fantasy_pos = limb.closest_point(segment, pos);
+            limb.insert_joint(segment, fantasy_pos);
         }
-        self.angles.push(std::f32::consts::PI);
+        if let Some(home) = self.initial_state.as_mut() {
+            let t = a.distance(pos) / a.distance(b).max(self.epsilon);
+            let home_pos = home.joints[segment].lerp(home.joints[segment + 1], t);
+            home.insert_joint(segment, home_pos);
+        }
+        self.recalculate_segments();
     }
     
-    pub fn solve(&mut self, iterations: usize, pose_discrepancy: PoseDiscrepancy, kinematics_mode: &mut KinematicsMode) {
-        match pose_discrepancy {
-            PoseDiscrepancy::WithinTolerance => {
-                *kinematics_mode = KinematicsMode::InverseKinematics;
-                self.recalculate_angles();
-                for _ in 0..iterations {
-                    for (index, pos) in self.targets.iter() {
-                        self.joints[*index] = *pos;
-                    }
-                    self.fwd_reach();
-                    if self.lock_ground {
-                        self.joints.first_mut().unwrap().clone_from(&Vec3::ZERO);
-                    }
-                    self.bwd_reach();
-                    for i in 0..self.joints.len() {
-                        dbg!(self.angles[i]);
-                    }
-                }
+    fn direction_or(&self, delta: Vec3, fallback: Vec3) -> Vec3 {
+        let length = delta.length();
+        if length > self.epsilon { delta / length } else { fallback }
+    }
+    
+    fn prior_direction(&self, segment: usize) -> Vec3 {
+        self.segment_directions.get(segment).copied().unwrap_or(Vec3::X)
+    }
+    
+    fn begin_pass(&mut self, pass: ReachPass) {
+        self.pass_touched.clear();
+        self.pass_touched.resize(self.joints.len(), ReachPass::Untouched);
+        self.last_pass = pass;
+    }
+    
+    fn clamp_bend(&mut self, pivot: usize, fixed: usize, moving: usize) {
+        let (min, max) = self.angle_limit(pivot);
+        let u = self.joints[fixed] - self.joints[pivot];
+        let v = self.joints[moving] - self.joints[pivot];
+        let angle = u.angle_between(v);
+        let clamped = angle.clamp(min, max);
+        if !angle.is_finite() || (clamped - angle).abs() <= f32::EPSILON {
+            return;
+        }
+        let axis = u
+            .cross(v)
+            .try_normalize()
+            .unwrap_or_else(|| u.normalize_or(Vec3::X).any_orthonormal_vector());
+        self.joints[moving] = self.joints[pivot] + Quat::from_axis_angle(axis, clamped - angle) * v;
+    }
+    
+    pub fn fwd_reach(&mut self) {
+        self.fwd_reach_span(0);
+    }
+    
+    pub fn bwd_reach(&mut self) {
+        self.bwd_reach_span(0);
+    }
+    
+    fn fwd_reach_span(&mut self, base: usize) {
+        self.begin_pass(ReachPass::Forward);
+        for i in (base..self.joints.len() - 1).rev() {
+            let (a, b) = (self.joints[i], self.joints[i + 1]);
+            let direction = self.direction_or(a - b, -self.prior_direction(i));
+            self.joints[i] = b + direction * self.lengths[i];
+            if i + 2 < self.joints.len() {
+                self.clamp_bend(i + 1, i + 2, i);
             }
-            PoseDiscrepancy::MildDivergence => {
-                *kinematics_mode = KinematicsMode::ForwardKinematics;
-                for i in 0..self.joints.len() {
-                    let residual_vec = self.limb.as_ref().unwrap().joints[i] - self.joints[i];
-                    let infintesimal_approximation = residual_vec / 2.0;
-                    let r_hat = residual_vec.normalize();
-                    let r_hat_div_angle = r_hat / self.angles[i];
-                    dbg!(r_hat_div_angle); 
-                }
+            if let Some(anchor) = self.anchor_position(i) {
+                self.joints[i] = anchor;
             }
-            PoseDiscrepancy::SevereDivergence => {
-                todo!();
+            self.pass_touched[i] = ReachPass::Forward;
+        }
+    }
+    
+    fn bwd_reach_span(&mut self, base: usize) {
+        self.begin_pass(ReachPass::Backward);
+        for i in base..self.joints.len() - 1 {
+            let (a, b) = (self.joints[i], self.joints[i + 1]);
+            let direction = self.direction_or(b - a, self.prior_direction(i));
+            self.joints[i + 1] = a + direction * self.lengths[i];
+            if i >= 1 {
+                self.clamp_bend(i, i - 1, i + 1);
+            }
+            if let Some(anchor) = self.anchor_position(i + 1) {
+                self.joints[i + 1] = anchor;
+            }
+            self.pass_touched[i + 1] = ReachPass::Backward;
+        }
+    }
+    
+    pub fn goals_reachable(&self, goals: &[(usize, Vec3)]) -> bool {
+        let base = self.solve_span_base(goals);
+        let root_goal = goals.iter().find(|(index, _)| *index == 0).map(|(_, pos)| *pos);
+        if base == 0 && !self.lock_ground && root_goal.is_none() {
+            return true;
+        }
+        let anchor = match root_goal {
+            Some(root) if base == 0 => root,
+            _ => self.joints[base],
+        };
+        let tip = self.joints.len() - 1;
+        goals
+            .iter()
+            .filter(|(index, _)| *index > base)
+            .all(|(index, pos)| {
+                let mut reach: f32 = self.lengths[base..*index].iter().sum();
+                if *index == tip {
+                    reach += self.tool_offset.length();
+                }
+                anchor.distance(*pos) <= reach + self.tolerance
+            })
+    }
+    
+    pub fn solve_span_base(&self, goals: &[(usize, Vec3)]) -> usize {
+        let Some(lowest) = goals.iter().map(|(index, _)| *index).min() else { return 0; };
+        (1..lowest)
+            .rev()
+            .find(|&i| self.is_frozen(i) || self.is_anchored(i))
+            .unwrap_or(0)
+    }
+    
+    pub fn step_half_pass(&mut self) {
+        if self.last_pass == ReachPass::Forward {
+            if self.lock_ground {
+                self.joints[0] = Vec3::ZERO;
             }
-            PoseDiscrepancy::EnvironmentalCompensation => {
-                todo!();
+            self.bwd_reach();
+        } else {
+            for (index, pos) in self.targets.clone() {
+                self.joints[index] = pos;
             }
+            self.fwd_reach();
         }
         self.recalculate_segments();
     }
-}
\ No newline at end of file
+    
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("graph chain {\n");
+        for (i, joint) in self.joints.iter().enumerate() {
+            writeln!(dot, "    j{i} [label=\"J{i} ({:.2}, {:.2}, {:.2})\"];", joint.x, joint.y, joint.z).unwrap();
+        }
+        for (i, length) in self.lengths.iter().enumerate() {
+            writeln!(dot, "    j{i} -- j{} [label=\"{length:.2}\"];", i + 1).unwrap();
+        }
+        dot.push('}');
+        dot
+    }
+    
+    pub fn project_to_plane(&self, normal: Vec3) -> Vec<Vec2> {
+        let (u, v) = normal.normalize().any_orthonormal_pair();
+        self.joints
+            .iter()
+            .map(|joint| Vec2::new(joint.dot(u), joint.dot(v)))
+            .collect()
+    }
+    
+    pub fn to_svg(&self, path: impl AsRef<Path>, normal: Vec3) -> io::Result<()> {
+        const SCALE: f32 = 100.0;
+        const MARGIN: f32 = 20.0;
+        
+        let points = self.project_to_plane(normal);
+        let (min, max) = points.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), point| (min.min(*point), max.max(*point))
+        );
+        let size = (max - min) * SCALE + Vec2::splat(MARGIN * 2.0);
+        let to_svg_space = |point: &Vec2| Vec2::new(
+            (point.x - min.x) * SCALE + MARGIN,
+            (max.y - point.y) * SCALE + MARGIN,
+        );
+        
+        let mut svg = String::new();
+        writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.0}\" height=\"{:.0}\">", size.x, size.y).unwrap();
+        let polyline = points
+            .iter()
+            .map(|point| {
+                let point = to_svg_space(point);
+                format!("{:.2},{:.2}", point.x, point.y)
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(svg, "  <polyline points=\"{polyline}\" fill=\"none\" stroke=\"black\" stroke-width=\"4\"/>").unwrap();
+        for point in points.iter().map(to_svg_space) {
+            writeln!(svg, "  <circle cx=\"{:.2}\" cy=\"{:.2}\" r=\"8\" fill=\"gray\"/>", point.x, point.y).unwrap();
+        }
+        svg.push_str("</svg>\n");
+        
+        std::fs::write(path, svg)
+    }
+    
+    #[cfg(feature = "serde")]
+    pub fn save_bincode(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let bytes = bincode::serde::encode_to_vec(self, bincode::config::standard())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, bytes)
+    }
+    
+    #[cfg(feature = "serde")]
+    pub fn save_json(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+    
+    #[cfg(feature = "serde")]
+    pub fn load_json(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let chain: Self = serde_json::from_str(&contents).map_err(io::Error::other)?;
+        Self::validate_loaded(chain)
+    }
+    
+    #[cfg(feature = "serde")]
+    pub fn load_bincode(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        let (chain, _): (Self, _) = bincode::serde::decode_from_slice(&bytes, bincode::config::standard())
+            .map_err(io::Error::other)?;
+        Self::validate_loaded(chain)
+    }
+    
+    #[cfg(feature = "serde")]
+    fn validate_loaded(mut chain: Self) -> io::Result<Self> {
+        let mut pending = vec![&chain];
+        while let Some(chain) = pending.pop() {
+            if chain.joints.len() < 2 || chain.lengths.len() + 1 != chain.joints.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected at least 2 joints with one length per segment, found {} joints and {} lengths", chain.joints.len(), chain.lengths.len())
+                ));
+            }
+            pending.extend(chain.limb.as_deref());
+            pending.extend(chain.initial_state.as_deref());
+        }
+        if let Some(limb) = chain.limb.as_ref().filter(|limb| limb.joints.len() != chain.joints.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected a limb with {} joints, found {}", chain.joints.len(), limb.joints.len())
+            ));
+        }
+        chain.sanitize();
+        Ok(chain)
+    }
+    
+    #[cfg(feature = "serde")]
+    pub fn export_transforms_ron(history: &[Vec<Transform>], path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(history, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+    
+    pub fn project_to_camera(&self, camera: &Transform) -> Vec<Vec2> {
+        self.joints
+            .iter()
+            .map(|joint| {
+                let local = camera.rotation.inverse() * (*joint - camera.translation);
+                Vec2::new(local.x / -local.z, local.y / -local.z)
+            })
+            .collect()
+    }
+    
+    pub fn fit_to_2d(&mut self, keypoints: &[Vec2], camera: &Transform) -> SolveReport {
+        self.targets = keypoints
+            .iter()
+            .take(self.joints.len())
+            .enumerate()
+            .map(|(i, keypoint)| {
+                let direction = (camera.rotation * Vec3::new(keypoint.x, keypoint.y, -1.0)).normalize();
+                let depth = (self.joints[i] - camera.translation).dot(direction).max(0.0);
+                (i, camera.translation + direction * depth)
+            })
+            .collect();
+        self.solve(FIT_ITERATIONS, PoseDiscrepancy::default(), &mut KinematicsMode::default())
+    }
+    
+    pub fn rest_deviations(&self) -> Vec<f32> {
+        self.angles
+            .iter()
+            .zip(self.rest_angles.iter())
+            .map(|(angle, rest)| (angle - rest).abs())
+            .collect()
+    }
+    
+    pub fn point_at_arclength(&self, s: f32) -> Vec3 {
+        let mut remaining = s.max(0.0);
+        for pair in self.joints.windows(2) {
+            let length = pair[0].distance(pair[1]);
+            if remaining <= length {
+                let t = if length > self.epsilon { remaining / length } else { 0.0 };
+                return pair[0].lerp(pair[1], t);
+            }
+            remaining -= length;
+        }
+        *self.joints.last().unwrap()
+    }
+    
+    pub fn diff(&self, other: &Self) -> Vec<Vec3> {
+        if self.joints.len() != other.joints.len() {
+            warn!("Diffing chains with {} and {} joints", self.joints.len(), other.joints.len());
+        }
+        self.joints
+            .iter()
+            .zip(other.joints.iter())
+            .map(|(a, b)| b - a)
+            .collect()
+    }
+    
+    pub fn relative_angles(&self) -> Vec<Quat> {
+        self.segment_transforms
+            .windows(2)
+            .map(|pair| pair[0].rotation.inverse() * pair[1].rotation)
+            .collect()
+    }
+    
+    pub fn jacobian(&self) -> Vec<Vec3> {
+        let ee = self.get_ee();
+        self.joints[..self.joints.len() - 1]
+            .iter()
+            .flat_map(|joint| [Vec3::X, Vec3::Y, Vec3::Z].map(|axis| axis.cross(ee - *joint)))
+            .collect()
+    }
+    
+    pub fn manipulability(&self) -> f32 {
+        let jjt = self
+            .jacobian()
+            .into_iter()
+            .fold(Mat3::ZERO, |acc, col| acc + Mat3::from_cols(col * col.x, col * col.y, col * col.z));
+        jjt.determinant().max(0.0).sqrt()
+    }
+    
+    fn tip_target_on_root(&self) -> bool {
+        let root = self.joints[0];
+        let tip = self.joints.len() - 1;
+        self.targets
+            .iter()
+            .any(|(index, pos)| *index == tip && pos.distance(root) < DEGENERATE_EPSILON)
+    }
+    
+    pub fn fold(&mut self) {
+        let direction = (self.joints[1] - self.joints[0])
+            .try_normalize()
+            .unwrap_or(Vec3::X);
+        for i in 0..self.lengths.len() {
+            let sign = if i % 2 == 0 { 1.0 } else { -1.0 };
+            self.joints[i+1] = self.joints[i] + direction * sign * self.lengths[i];
+        }
+    }
+    
+    fn measure_angles(&self) -> Vec<f32> {
+        self.joints
+            .windows(3)
+            .map(|w| (w[0] - w[1]).angle_between(w[2] - w[1]))
+            .collect()
+    }
+    
+    // Each axis is stored relative to the turns of the joints before it, so FK edits carry later bends along.
+    fn measure_bend_axes(&self) -> Vec<Vec3> {
+        let mut frame = Quat::IDENTITY;
+        let mut fallback = self.bend_normal();
+        self.joints
+            .windows(3)
+            .enumerate()
+            .map(|(i, w)| {
+                let (incoming, outgoing) = (w[1] - w[0], w[2] - w[1]);
+                let axis = incoming
+                    .cross(outgoing)
+                    .try_normalize()
+                    .or_else(|| self.bend_axes.get(i).map(|local| frame * *local))
+                    .unwrap_or(fallback);
+                let local = frame.inverse() * axis;
+                frame = Quat::from_axis_angle(axis, incoming.angle_between(outgoing)) * frame;
+                fallback = axis;
+                local
+            })
+            .collect()
+    }
+    
+    pub fn recalculate_angles(&mut self) {
+        let angles = self.measure_angles();
+        self.bend_axes = self.measure_bend_axes();
+        self.prev_angles = std::mem::replace(&mut self.angles, angles);
+    }
+    
+    fn stretched_lengths(&self, goals: &[(usize, Vec3)]) -> Vec<f32> {
+        let tip = self.joints.len() - 1;
+        let (Some(tolerance), Some((_, goal))) = (
+            self.stretch_tolerance,
+            goals.iter().find(|(index, _)| *index == tip)
+        ) else {
+            return self.lengths.clone();
+        };
+        
+        let rest_total: f32 = self.lengths.iter().sum();
+        let mut allowed = rest_total * (1.0 + tolerance);
+        if let Some(budget) = self.length_budget {
+            allowed = allowed.min(budget.max(rest_total));
+        }
+        let factor = goal.distance(self.joints[0]).clamp(rest_total, allowed) / rest_total;
+        self.lengths.iter().map(|length| length * factor).collect()
+    }
+    
+    fn lead_targets(&mut self) -> Vec<(usize, Vec3)> {
+        let dt = self
+            .prev_time
+            .elapsed()
+            .map(|elapsed| elapsed.as_secs_f32())
+            .unwrap_or(0.0);
+        let goals = self
+            .targets
+            .iter()
+            .map(|(index, pos)| {
+                let velocity = self
+                    .prev_targets
+                    .iter()
+                    .find(|(prev_index, _)| prev_index == index)
+                    .filter(|_| dt > 0.0)
+                    .map(|(_, prev_pos)| (*pos - *prev_pos) / dt)
+                    .unwrap_or(Vec3::ZERO);
+                (*index, *pos + velocity * self.prediction_time)
+            })
+            .collect();
+        self.prev_targets = self.targets.clone();
+        goals
+    }
+    
+    pub fn bend_normal(&self) -> Vec3 {
+        self.joints
+            .windows(3)
+            .find_map(|w| (w[1] - w[0]).cross(w[2] - w[1]).try_normalize())
+            .unwrap_or(Vec3::Z)
+    }
+    
+    pub fn apply_forward_kinematics(&mut self) {
+        self.place_from_angles();
+        self.recalculate_segments();
+    }
+    
+    fn place_from_angles(&mut self) {
+        if self.angles.len() + 1 != self.lengths.len() {
+            return;
+        }
+        let Some(first_direction) = (self.joints[1] - self.joints[0]).try_normalize() else {
+            return;
+        };
+        let normal = self.bend_normal();
+        
+        self.joints[1] = self.joints[0] + first_direction * self.lengths[0];
+        let mut direction = first_direction;
+        let mut frame = Quat::IDENTITY;
+        for i in 1..self.lengths.len() {
+            let axis = frame * self.bend_axes.get(i - 1).copied().unwrap_or(normal);
+            let turn = Quat::from_axis_angle(axis, std::f32::consts::PI - self.angles[i - 1]);
+            direction = turn * direction;
+            frame = turn * frame;
+            self.joints[i+1] = self.joints[i] + direction * self.lengths[i];
+        }
+    }
+    
+    fn equalize_angular_velocity(&mut self, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize {
+        let turns: Vec<f32> = prev_joints
+            .windows(2)
+            .zip(self.joints.windows(2))
+            .map(|(before, after)| (before[1] - before[0]).angle_between(after[1] - after[0]))
+            .map(|turn| if turn.is_finite() { turn } else { 0.0 })
+            .collect();
+        let mean_turn = turns.iter().sum::<f32>() / turns.len() as f32;
+        if mean_turn <= self.epsilon {
+            return 0;
+        }
+        
+        for (i, turn) in turns.iter().enumerate() {
+            let before = self.direction_or(prev_joints[i + 1] - prev_joints[i], self.prior_direction(i));
+            let after = self.direction_or(self.joints[i + 1] - self.joints[i], before);
+            let direction = if *turn > mean_turn {
+                Quat::IDENTITY.slerp(Quat::from_rotation_arc(before, after), mean_turn / turn) * before
+            } else {
+                after
+            };
+            self.joints[i + 1] = self.joints[i] + direction * self.lengths[i];
+        }
+        self.reach_iterations(goals, prev_joints, iterations)
+    }
+    
+    fn refine(&mut self, pass: impl FnOnce(&mut Self) -> usize) -> usize {
+        let hook = self.on_iteration.take();
+        let errors = std::mem::take(&mut self.iteration_errors);
+        let iterations_run = pass(self);
+        self.iteration_errors = errors;
+        self.on_iteration = hook;
+        iterations_run
+    }
+    
+    // How far each joint would turn in a Jacobian-transpose step toward the perturbed target.
+    pub fn sensitivity(&self, target_delta: Vec3) -> Vec<f32> {
+        let ee = self.get_ee();
+        let tip = self.joints.len() - 1;
+        self.joints
+            .iter()
+            .enumerate()
+            .map(|(i, joint)| {
+                if i == tip || self.is_frozen(i) {
+                    return 0.0;
+                }
+                let moment = (ee - *joint).cross(target_delta);
+                self.hinge_axis(i).map_or(moment.length(), |axis| moment.dot(axis).abs())
+            })
+            .collect()
+    }
+    
+    pub fn round_trip_residual(&self, perturbation: Vec3, iterations: usize) -> f32 {
+        let mut chain = self.clone();
+        chain.on_iteration = None;
+        let tip = chain.joints.len() - 1;
+        chain.targets = vec![(tip, chain.get_ee() + perturbation)];
+        chain.solve(iterations, PoseDiscrepancy::default(), &mut KinematicsMode::default());
+        
+        let solved = chain.joints.clone();
+        chain.recalculate_angles();
+        chain.apply_forward_kinematics();
+        
+        solved
+            .iter()
+            .zip(chain.joints.iter())
+            .map(|(ik, fk)| ik.distance(*fk))
+            .fold(0.0, f32::max)
+    }
+    
+    pub fn solve_blended(&mut self, iterations: usize, pose_discrepancy: PoseDiscrepancy, kinematics_mode: &mut KinematicsMode) -> SolveReport {
+        if self.fk_ik_blend >= 1.0 {
+            return self.solve(iterations, pose_discrepancy, kinematics_mode);
+        }
+        if self.angles.len() != self.joints.len() - 2 {
+            self.recalculate_angles();
+        }
+        
+        let mut fk = self.clone();
+        fk.apply_forward_kinematics();
+        let report = self.solve(iterations, pose_discrepancy, kinematics_mode);
+        
+        let blend = self.fk_ik_blend.clamp(0.0, 1.0);
+        for (joint, fk_joint) in self.joints.iter_mut().zip(fk.joints.iter()) {
+            *joint = fk_joint.lerp(*joint, blend);
+        }
+        self.recalculate_segments();
+        
+        let final_error = self.target_error();
+        SolveReport {
+            final_error,
+            converged: final_error <= self.tolerance,
+            ..report
+        }
+    }
+    
+    pub fn solve_batch(&mut self, targets: &[Vec3], iterations: usize) -> usize {
+        let tip = self.joints.len() - 1;
+        let mut kinematics_mode = KinematicsMode::default();
+        let mut total_iterations = 0;
+        for target in targets {
+            self.targets.clear();
+            self.targets.push((tip, *target));
+            total_iterations += self
+                .solve(iterations, PoseDiscrepancy::default(), &mut kinematics_mode)
+                .iterations;
+        }
+        total_iterations
+    }
+    
+    pub fn end_iteration(&mut self, iteration: usize) {
+        self.iteration_errors.push(self.target_error());
+        if self.debug_solve {
+            debug!(iteration, joints = ?self.joints, angles = ?self.measure_angles(), "solve iteration");
+        }
+        self.run_iteration_hook(iteration);
+    }
+    
+    fn run_iteration_hook(&self, iteration: usize) {
+        if let Some(hook) = self.on_iteration.as_ref() {
+            let mut hook = hook.0.lock().unwrap();
+            hook(self, iteration);
+        }
+    }
+    
+    fn tip_converged(&self, goals: &[(usize, Vec3)]) -> bool {
+        let tip = self.joints.len() - 1;
+        goals
+            .iter()
+            .find(|(index, _)| *index == tip)
+            .is_some_and(|(_, pos)| self.get_ee().distance(*pos) <= self.tolerance)
+    }
+    
+    fn reach_iterations(&mut self, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize {
+        match self.custom_solver.clone() {
+            Some(custom) => custom.0.solve(self, goals, prev_joints, iterations),
+            None => {
+                let solver = self.solver;
+                solver.solve(self, goals, prev_joints, iterations)
+            }
+        }
+    }
+    
+    pub fn fabrik_iterations(&mut self, goals: &[(usize, Vec3)], prev_joints: &[Vec3], iterations: usize) -> usize {
+        let base = self.solve_span_base(goals);
+        if base > 0 {
+            return self.reach_span_iterations(goals, prev_joints, base, iterations);
+        }
+        let root_goal = goals.iter().find(|(index, _)| *index == 0).map(|(_, pos)| *pos);
+        for iteration in 0..iterations {
+            for (index, pos) in goals.iter() {
+                self.place_goal(*index, *pos);
+            }
+            for i in 0..self.joints.len() {
+                if self.is_frozen(i) {
+                    self.joints[i] = prev_joints[i];
+                }
+            }
+            self.fwd_reach();
+            if self.lock_ground {
+                self.joints.first_mut().unwrap().clone_from(&Vec3::ZERO);
+            } else if let Some(root) = root_goal {
+                self.joints[0] = root;
+            }
+            self.project_onto_environment();
+            self.bwd_reach();
+            self.apply_pole(0);
+            self.apply_hinges();
+            self.apply_distance_constraints();
+            self.project_onto_environment();
+            self.end_iteration(iteration);
+            if self.tip_converged(goals) {
+                return iteration + 1;
+            }
+        }
+        iterations
+    }
+    
+    fn rotate_descendants(&mut self, pivot: usize, rotation: Quat) {
+        let origin = self.joints[pivot];
+        for joint in self.joints[pivot + 1..].iter_mut() {
+            *joint = origin + rotation * (*joint - origin);
+        }
+    }
+    
+    fn ccd_rotate(&mut self, pivot: usize, effector: usize, goal: Vec3) {
+        let tip = self.joints.len() - 1;
+        let origin = self.joints[pivot];
+        let effector_pos = if effector == tip { self.get_ee() } else { self.joints[effector] };
+        let (Some(from), Some(to)) = ((effector_pos - origin).try_normalize(), (goal - origin).try_normalize()) else {
+            return;
+        };
+        self.rotate_descendants(pivot, Quat::from_rotation_arc(from, to));
+        
+        if pivot >= 1 {
+            let child = self.joints[pivot + 1];
+            self.clamp_bend(pivot, pivot - 1, pivot + 1);
+            let clamped = std::mem::replace(&mut self.joints[pivot + 1], child);
+            if let (Some(before), Some(after)) = ((child - origin).try_normalize(), (clamped - origin).try_normalize()) {
+                self.rotate_descendants(pivot, Quat::from_rotation_arc(before, after));
+            }
+        }
+    }
+    
+    pub fn ccd_iterations(&mut self, goals: &[(usize, Vec3)], iterations: usize) -> usize {
+        for i in 0..self.lengths.len() {
+            let direction = self.direction_or(self.joints[i + 1] - self.joints[i], self.prior_direction(i));
+            let correction = self.joints[i] + direction * self.lengths[i] - self.joints[i + 1];
+            for joint in self.joints[i + 1..].iter_mut() {
+                *joint += correction;
+            }
+        }
+        
+        let mut goals = goals.to_vec();
+        goals.sort_by_key(|(index, _)| *index);
+        for iteration in 0..iterations {
+            for (effector, goal) in goals.iter().rev() {
+                for pivot in (0..*effector).rev() {
+                    if !(pivot + 1..self.joints.len()).any(|i| self.is_frozen(i)) {
+                        self.ccd_rotate(pivot, *effector, *goal);
+                    }
+                }
+            }
+            self.apply_hinges();
+            self.apply_distance_constraints();
+            self.project_onto_environment();
+            self.end_iteration(iteration);
+            if self.tip_converged(&goals) {
+                return iteration + 1;
+            }
+        }
+        iterations
+    }
+    
+    fn project_onto_environment(&mut self) {
+        if !self.compensating {
+            return;
+        }
+        let ground_height = self.motion_heuristics.ground_height;
+        let planes = &self.motion_heuristics.obstacle_planes;
+        for joint in self.joints.iter_mut() {
+            if let Some(height) = ground_height {
+                joint.y = joint.y.max(height);
+            }
+            for (normal, offset) in planes.iter() {
+                let depth = offset - normal.dot(*joint);
+                if depth > 0.0 {
+                    *joint += *normal * depth;
+                }
+            }
+        }
+    }
+    
+    fn apply_hinges(&mut self) {
+        for i in 0..self.joints.len() - 1 {
+            let Some(axis) = self.hinge_axis(i) else { continue; };
+            let offset = self.joints[i + 1] - self.joints[i];
+            let planar = offset - axis * offset.dot(axis);
+            let direction = self.direction_or(planar, axis.any_orthonormal_vector());
+            let correction = self.joints[i] + direction * self.lengths[i] - self.joints[i + 1];
+            for joint in self.joints[i + 1..].iter_mut() {
+                *joint += correction;
+            }
+        }
+    }
+    
+    fn apply_distance_constraints(&mut self) {
+        for i in 0..self.distance_constraints.len() {
+            let (a, b, distance) = self.distance_constraints[i];
+            if a >= self.joints.len() || b >= self.joints.len() || a == b {
+                continue;
+            }
+            let pinned = |index: usize| self.is_frozen(index) || (index == 0 && self.lock_ground);
+            let (pin_a, pin_b) = (pinned(a), pinned(b));
+            let delta = self.joints[b] - self.joints[a];
+            let Some(direction) = delta.try_normalize() else { continue; };
+            let correction = direction * (delta.length() - distance) * DISTANCE_STIFFNESS;
+            match (pin_a, pin_b) {
+                (true, true) => {}
+                (true, false) => self.joints[b] -= correction,
+                (false, true) => self.joints[a] += correction,
+                (false, false) => {
+                    self.joints[a] += correction * 0.5;
+                    self.joints[b] -= correction * 0.5;
+                }
+            }
+        }
+    }
+    
+    fn apply_tip_orientation(&mut self, goals: &[(usize, Vec3)], iterations: usize) -> usize {
+        let Some(rotation) = self.tip_orientation else {
+            self.tool_roll = 0.0;
+            return 0;
+        };
+        let tip = self.joints.len() - 1;
+        let direction = (rotation * Vec3::Y).normalize();
+        let mut iterations_run = 0;
+        if tip >= 2 {
+            let anchor = goals
+                .iter()
+                .find(|(index, _)| *index == tip)
+                .map_or_else(|| self.get_ee(), |(_, pos)| *pos);
+            let wrist = anchor - rotation * self.tool_offset - direction * self.lengths[tip - 1];
+            
+            let (limb, home) = (self.limb.take(), self.initial_state.take());
+            let mut arm = self.clone();
+            (self.limb, self.initial_state) = (limb, home);
+            arm.on_iteration = None;
+            arm.tip_orientation = None;
+            arm.tool_offset = Vec3::ZERO;
+            arm.joints.truncate(tip);
+            arm.sanitize_pose();
+            arm.lengths = self.lengths[..tip - 1].to_vec();
+            let arm_prev = arm.joints.clone();
+            iterations_run = arm.reach_iterations(&[(tip - 1, wrist)], &arm_prev, iterations);
+            self.joints[..tip].copy_from_slice(&arm.joints);
+        }
+        self.joints[tip] = self.joints[tip - 1] + direction * self.lengths[tip - 1];
+        let twist = segment_rotation(self.joints[tip], self.joints[tip - 1], self.up_axis).inverse() * rotation;
+        self.tool_roll = 2.0 * twist.y.atan2(twist.w);
+        iterations_run
+    }
+    
+    fn resolve_root_target(&mut self, mut goals: Vec<(usize, Vec3)>) -> Vec<(usize, Vec3)> {
+        let Some(slot) = goals.iter().position(|(index, _)| *index == 0) else { return goals; };
+        if self.lock_ground {
+            warn!("Ignoring the root target while the ground is locked");
+            goals.remove(slot);
+            return goals;
+        }
+        let offset = goals[slot].1 - self.joints[0];
+        for joint in self.joints.iter_mut() {
+            *joint += offset;
+        }
+        goals
+    }
+    
+    #[cfg(debug_assertions)]
+    pub fn solve_traced(&mut self, iterations: usize) -> Vec<Vec<Vec3>> {
+        let prev_joints = self.joints.clone();
+        self.recalculate_angles();
+        let goals = self.lead_targets();
+        let goals = self.resolve_root_target(goals);
+        
+        let mut trace = vec![self.joints.clone()];
+        for _ in 0..iterations {
+            self.reach_iterations(&goals, &prev_joints, 1);
+            trace.push(self.joints.clone());
+            if self.tip_converged(&goals) {
+                break;
+            }
+        }
+        self.recalculate_segments();
+        trace
+    }
+    
+    fn reach_span_iterations(&mut self, goals: &[(usize, Vec3)], prev_joints: &[Vec3], base: usize, iterations: usize) -> usize {
+        let base_pos = self.anchor_position(base).unwrap_or(prev_joints[base]);
+        for iteration in 0..iterations {
+            for (index, pos) in goals.iter() {
+                self.place_goal(*index, *pos);
+            }
+            for i in base..self.joints.len() {
+                if self.is_frozen(i) {
+                    self.joints[i] = prev_joints[i];
+                }
+            }
+            self.fwd_reach_span(base);
+            self.joints[base] = base_pos;
+            self.project_onto_environment();
+            self.bwd_reach_span(base);
+            self.apply_pole(base);
+            self.apply_hinges();
+            self.apply_distance_constraints();
+            self.project_onto_environment();
+            self.end_iteration(iteration);
+            if self.tip_converged(goals) {
+                return iteration + 1;
+            }
+        }
+        iterations
+    }
+    
+    fn apply_pole(&mut self, base: usize) {
+        let Some(pole) = self.pole_target else { return; };
+        let tip = self.joints.len() - 1;
+        if tip < base + 2 || (base + 1..tip).any(|i| self.is_frozen(i)) {
+            return;
+        }
+        let origin = self.joints[base];
+        let Some(axis) = (self.joints[tip] - origin).try_normalize() else { return; };
+        let reject = |point: Vec3| {
+            let offset = point - origin;
+            offset - axis * offset.dot(axis)
+        };
+        
+        let bend = (base + 1..tip)
+            .map(|i| reject(self.joints[i]))
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap();
+        let (Some(bend), Some(toward)) = (bend.try_normalize(), reject(pole).try_normalize()) else {
+            return;
+        };
+        let angle = bend.cross(toward).dot(axis).atan2(bend.dot(toward));
+        let rotation = Quat::from_axis_angle(axis, angle);
+        for joint in self.joints[base + 1..tip].iter_mut() {
+            *joint = origin + rotation * (*joint - origin);
+        }
+    }
+    
+    fn flipped_joints(&self, prev_joints: &[Vec3]) -> Vec<usize> {
+        (1..self.joints.len().saturating_sub(1))
+            .filter(|&i| bend_offset(prev_joints, i).dot(bend_offset(&self.joints, i)) < 0.0)
+            .collect()
+    }
+    
+    fn target_error(&self) -> f32 {
+        self.targets
+            .iter()
+            .map(|(index, pos)| self.effector_position(*index).distance(*pos))
+            .fold(0.0, f32::max)
+    }
+    
+    fn warm_start_key(&self) -> WarmStartKey {
+        self.targets
+            .iter()
+            .map(|(index, pos)| (*index, (*pos / WARM_START_QUANTUM).round().as_ivec3()))
+            .collect()
+    }
+    
+    fn recall_warm_start(&mut self, key: &WarmStartKey) -> bool {
+        let joint_count = self.joints.len();
+        let Some(slot) = self.warm_start_cache
+            .iter()
+            .position(|(cached, joints)| cached == key && joints.len() == joint_count)
+        else {
+            return false;
+        };
+        let entry = self.warm_start_cache.remove(slot).unwrap();
+        self.joints.clone_from(&entry.1);
+        self.warm_start_cache.push_front(entry);
+        true
+    }
+    
+    fn store_warm_start(&mut self, key: WarmStartKey) {
+        self.warm_start_cache.retain(|(cached, _)| *cached != key);
+        self.warm_start_cache.push_front((key, self.joints.clone()));
+        self.warm_start_cache.truncate(WARM_START_CAPACITY);
+    }
+    
+    pub fn targets_reached(&self) -> Vec<(usize, bool)> {
+        self.targets
+            .iter()
+            .map(|(index, pos)| (*index, self.effector_position(*index).distance(*pos) <= self.tolerance))
+            .collect()
+    }
+    
+    pub fn solve(&mut self, iterations: usize, pose_discrepancy: PoseDiscrepancy, kinematics_mode: &mut KinematicsMode) -> SolveReport {
+        let prev_joints = self.joints.clone();
+        let warm_start_key = (self.warm_start && !self.targets.is_empty()).then(|| self.warm_start_key());
+        self.iteration_errors.clear();
+        let mut reachable = true;
+        let mut refinement_iterations = 0;
+        let iterations_run = match pose_discrepancy {
+            PoseDiscrepancy::WithinTolerance | PoseDiscrepancy::EnvironmentalCompensation => {
+                *kinematics_mode = KinematicsMode::InverseKinematics;
+                self.compensating = pose_discrepancy == PoseDiscrepancy::EnvironmentalCompensation;
+                self.recalculate_angles();
+                let goals = self.lead_targets();
+                let goals = self.resolve_root_target(goals);
+                let stretched_lengths = self.stretched_lengths(&goals);
+                let rest_lengths = std::mem::replace(&mut self.lengths, stretched_lengths);
+                reachable = self.goals_reachable(&goals);
+                let iterations_run = if self.tip_target_on_root() {
+                    self.fold();
+                    0
+                } else {
+                    if let Some(key) = warm_start_key.as_ref() {
+                        self.recall_warm_start(key);
+                    }
+                    let iterations_run = self.reach_iterations(&goals, &prev_joints, iterations);
+                    let flipped = self.flipped_joints(&prev_joints);
+                    if self.prevent_flips && !flipped.is_empty() {
+                        self.joints = prev_joints.clone();
+                        for i in flipped {
+                            let bias = bend_offset(&prev_joints, i).normalize_or_zero() * self.lengths[i] * FLIP_BIAS;
+                            self.joints[i] += bias;
+                        }
+                        iterations_run + self.reach_iterations(&goals, &prev_joints, iterations)
+                    } else {
+                        iterations_run
+                    }
+                };
+                if self.equalize_velocity {
+                    refinement_iterations += self.refine(|chain| chain.equalize_angular_velocity(&goals, &prev_joints, iterations));
+                }
+                refinement_iterations += self.apply_tip_orientation(&goals, iterations);
+                if self.avoid_self_collision && !self.resolve_self_collisions() {
+                    warn!("Could not resolve self-collisions in the solved pose");
+                }
+                self.lengths = rest_lengths;
+                self.compensating = false;
+                iterations_run
+            }
+            PoseDiscrepancy::MildDivergence => {
+                *kinematics_mode = KinematicsMode::ForwardKinematics;
+                let limb = self.limb.as_ref().filter(|limb| limb.joints.len() == self.joints.len());
+                if let Some(limb) = limb {
+                    for (i, angle) in self.angles.iter().enumerate() {
+                        let residual_vec = limb.joints[i + 1] - self.joints[i + 1];
+                        let infintesimal_approximation = residual_vec / 2.0;
+                        let r_hat = residual_vec.normalize();
+                        let r_hat_div_angle = r_hat / *angle;
+                        trace!(joint = i + 1, ?infintesimal_approximation, ?r_hat_div_angle, "mild divergence residual");
+                    }
+                }
+                0
+            }
+            PoseDiscrepancy::SevereDivergence => {
+                *kinematics_mode = KinematicsMode::InverseKinematics;
+                self.recalculate_angles();
+                let known_good = self.limb
+                    .as_ref()
+                    .map(|limb| limb.joints.clone())
+                    .filter(|joints| joints.len() == self.joints.len());
+                if let Some(known_good) = known_good {
+                    let residual = self.joints
+                        .iter()
+                        .zip(known_good.iter())
+                        .map(|(joint, good)| joint.distance(*good))
+                        .fold(0.0, f32::max);
+                    if residual > SEVERE_DIVERGENCE_THRESHOLD {
+                        warn!("Pose diverged by {residual:.3}, restoring the fantasy limb pose");
+                        self.joints = known_good;
+                    }
+                }
+                let goals = self.lead_targets();
+                let goals = self.resolve_root_target(goals);
+                let reference = self.joints.clone();
+                reachable = self.goals_reachable(&goals);
+                self.reach_iterations(&goals, &reference, 1)
+            }
+        };
+        if self.joints.iter().any(|joint| !joint.is_finite()) {
+            warn!("Solve produced a non-finite pose, restoring the previous pose");
+            self.joints = prev_joints.clone();
+        }
+        self.recalculate_segments();
+        
+        let final_error = self.target_error();
+        let converged = final_error <= self.tolerance;
+        let max_joint_delta = prev_joints
+            .iter()
+            .zip(self.joints.iter())
+            .map(|(before, after)| before.distance(*after))
+            .fold(0.0, f32::max);
+        if let Some(key) = warm_start_key.filter(|_| converged) {
+            self.store_warm_start(key);
+        }
+        SolveReport {
+            iterations: iterations_run,
+            refinement_iterations,
+            final_error,
+            converged,
+            reachable,
+            max_joint_delta,
+            error_history: std::mem::take(&mut self.iteration_errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    
+    fn straight_chain(joint_count: usize) -> FabrikChain {
+        let joints = (0..joint_count).map(|i| Vec3::X * i as f32).collect();
+        FabrikChain::new(joints, MotionHueristics::default())
+    }
+    
+    fn advance_angles(chain: &mut FabrikChain, angles: Vec<f32>, seconds: f32) {
+        chain.prev_angles = std::mem::replace(&mut chain.angles, angles);
+        chain.prev_time = SystemTime::now() - Duration::from_secs_f32(seconds);
+        chain.recalculate_segments();
+    }
+    
+    fn solve_tip(chain: &mut FabrikChain, target: Vec3, iterations: usize) -> SolveReport {
+        let tip = chain.joints.len() - 1;
+        chain.targets = vec![(tip, target)];
+        chain.solve(iterations, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default())
+    }
+    
+    #[test]
+    fn static_torques_match_lever_arms_under_a_tip_load() {
+        let chain = straight_chain(3);
+        let torques = chain.static_torques(Vec3::new(0.0, -10.0, 0.0));
+        for (torque, expected) in torques.iter().zip([20.0, 10.0, 0.0]) {
+            assert!((torque - expected).abs() < 1e-4, "{torques:?}");
+        }
+        
+        let with_weight = chain.static_torques_with_self_weight(Vec3::new(0.0, -10.0, 0.0));
+        assert!((with_weight[0] - (20.0 + 2.0 * GRAVITY)).abs() < 1e-3, "{with_weight:?}");
+        assert!((with_weight[1] - (10.0 + 0.5 * GRAVITY)).abs() < 1e-3, "{with_weight:?}");
+    }
+    
+    #[test]
+    fn tip_target_on_the_root_folds_into_a_valid_pose() {
+        let mut chain = straight_chain(4);
+        let report = solve_tip(&mut chain, Vec3::ZERO, 10);
+        
+        assert!(chain.joints.iter().all(|joint| joint.is_finite()));
+        assert!(report.final_error.is_finite());
+        assert_eq!(chain.joints[0], Vec3::ZERO);
+        for (pair, length) in chain.joints.windows(2).zip(chain.lengths.iter()) {
+            assert!((pair[0].distance(pair[1]) - length).abs() < 1e-4);
+        }
+    }
+    
+    #[test]
+    fn manipulability_vanishes_as_the_chain_straightens() {
+        let elbow = |bend: f32| {
+            let joints = vec![Vec3::ZERO, Vec3::X, Vec3::X + Vec3::new(bend.cos(), bend.sin(), 0.0)];
+            FabrikChain::new(joints, MotionHueristics::default()).manipulability()
+        };
+        let values: Vec<f32> = [1.0, 0.5, 0.1, 0.0].into_iter().map(elbow).collect();
+        assert!(values.windows(2).all(|pair| pair[0] > pair[1]), "{values:?}");
+        assert!(values[3] < 1e-4, "{values:?}");
+    }
+    
+    #[test]
+    fn rest_deviation_is_zero_at_the_rest_pose() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)], MotionHueristics::default());
+        chain.recalculate_angles();
+        chain.rest_angles = chain.angles.clone();
+        assert_eq!(chain.rest_deviations().len(), 2);
+        assert!(chain.rest_deviations().iter().all(|deviation| deviation.abs() < 1e-6));
+        
+        chain.joints[3] = Vec3::new(1.0, 2.0, 0.0);
+        chain.recalculate_angles();
+        assert!(chain.rest_deviations()[1] > 0.1);
+    }
+    
+    #[test]
+    fn dot_export_lists_every_joint_and_segment() {
+        let dot = straight_chain(3).to_dot();
+        assert!(dot.starts_with("graph chain {"));
+        assert_eq!(dot.lines().filter(|line| line.contains("[label=\"J")).count(), 3);
+        assert_eq!(dot.lines().filter(|line| line.contains(" -- ")).count(), 2);
+    }
+    
+    #[test]
+    fn desynced_lengths_are_rebuilt_instead_of_panicking() {
+        let mut chain = straight_chain(4);
+        chain.lengths.pop();
+        chain.segment_transforms.push(Transform { translation: Vec3::ZERO, rotation: Quat::IDENTITY, scale: Vec3::ONE });
+        chain.recalculate_segments();
+        
+        assert_eq!(chain.segment_transforms.len(), 3);
+        assert_eq!(chain.lengths.len(), 3);
+        assert!(solve_tip(&mut chain, Vec3::new(1.0, 2.0, 0.0), 10).final_error < 1e-2);
+    }
+    
+    #[test]
+    fn bounds_enclose_known_joints() {
+        let chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 2.0, 0.0)], MotionHueristics::default());
+        assert_eq!(chain.aabb(), (Vec3::ZERO, Vec3::new(1.0, 2.0, 0.0)));
+        
+        let (center, radius) = chain.bounding_sphere();
+        assert_eq!(center, Vec3::new(0.5, 1.0, 0.0));
+        assert!((radius - 1.25f32.sqrt()).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn prediction_reduces_lag_behind_a_moving_target() {
+        let orbit = |frame: usize| {
+            let t = frame as f32 * 0.05;
+            Vec3::new(2.0 * t.cos(), 2.0 * t.sin(), 0.0)
+        };
+        let tracking_error = |prediction_time: f32| {
+            let mut chain = straight_chain(4);
+            chain.prediction_time = prediction_time;
+            let mut error = 0.0;
+            for frame in 0..30 {
+                chain.prev_time = SystemTime::now() - Duration::from_millis(100);
+                solve_tip(&mut chain, orbit(frame), 20);
+                if frame >= 10 {
+                    error += chain.get_ee().distance(orbit(frame + 1));
+                }
+            }
+            error
+        };
+        assert!(tracking_error(0.1) < tracking_error(0.0) * 0.5);
+    }
+    
+    #[test]
+    fn projecting_a_planar_chain_preserves_its_ordering() {
+        let joints = vec![Vec3::ZERO, Vec3::new(1.0, 2.0, 0.0), Vec3::new(3.0, 1.0, 0.0), Vec3::new(4.0, 3.0, 0.0)];
+        let chain = FabrikChain::new(joints.clone(), MotionHueristics::default());
+        let points = chain.project_to_plane(Vec3::Z);
+        
+        assert_eq!(points.len(), joints.len());
+        for i in 0..joints.len() {
+            for j in 0..joints.len() {
+                assert_eq!(points[i].x < points[j].x, joints[i].x < joints[j].x);
+                assert_eq!(points[i].y < points[j].y, joints[i].y < joints[j].y);
+            }
+        }
+    }
+    
+    #[test]
+    fn set_joint_updates_adjacent_lengths() {
+        let mut chain = straight_chain(4);
+        assert!(chain.set_joint(2, Vec3::new(1.0, 2.0, 0.0), None).is_none());
+        
+        assert!((chain.lengths[1] - 2.0).abs() < 1e-6);
+        assert!((chain.lengths[2] - 8.0f32.sqrt()).abs() < 1e-6);
+        assert_eq!(chain.lengths[0], 1.0);
+        assert_eq!(chain.segment_transforms[1].translation, Vec3::new(1.0, 1.0, 0.0));
+    }
+    
+    #[test]
+    fn round_trip_residual_is_small_on_a_bent_chain() {
+        let joints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.5)];
+        let chain = FabrikChain::new(joints, MotionHueristics::default());
+        let residual = chain.round_trip_residual(Vec3::new(0.1, 0.1, 0.1), 20);
+        assert!(residual < 1e-3, "residual {residual}");
+    }
+    
+    #[test]
+    fn center_of_mass_shifts_toward_heavier_segments() {
+        let mut chain = straight_chain(3);
+        assert!((chain.center_of_mass().x - 1.0).abs() < 1e-6);
+        
+        chain.segment_masses[1] = 3.0;
+        assert!((chain.center_of_mass().x - 1.25).abs() < 1e-6);
+        chain.segment_masses = vec![3.0, 1.0];
+        assert!((chain.center_of_mass().x - 0.75).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn non_finite_solve_restores_the_prior_pose() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)], MotionHueristics::default());
+        let before = chain.joints.clone();
+        chain.lengths[1] = f32::NAN;
+        solve_tip(&mut chain, Vec3::new(0.0, 2.0, 0.0), 10);
+        assert_eq!(chain.joints, before);
+    }
+    
+    #[test]
+    fn min_segments_follow_the_cumulative_length() {
+        let chain = straight_chain(5);
+        assert_eq!(chain.min_segments_for(Vec3::ZERO), 0);
+        assert_eq!(chain.min_segments_for(Vec3::new(0.0, 0.5, 0.0)), 1);
+        assert_eq!(chain.min_segments_for(Vec3::new(0.0, 2.0, 0.0)), 2);
+        assert_eq!(chain.min_segments_for(Vec3::new(0.0, 2.5, 0.0)), 3);
+        assert_eq!(chain.min_segments_for(Vec3::new(0.0, 10.0, 0.0)), 4);
+    }
+    
+    #[test]
+    fn stretch_never_exceeds_the_length_budget() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 3.0], MotionHueristics::default());
+        chain.stretch_tolerance = Some(1.0);
+        chain.length_budget = Some(4.0);
+        
+        let stretched = chain.stretched_lengths(&[(2, Vec3::X * 10.0)]);
+        assert!(stretched.iter().sum::<f32>() <= 4.0 + 1e-5, "{stretched:?}");
+        assert!((stretched[1] / stretched[0] - 2.0).abs() < 1e-5, "{stretched:?}");
+        
+        solve_tip(&mut chain, Vec3::X * 10.0, 10);
+        assert!(chain.get_ee().length() <= 4.0 + 1e-4);
+        assert_eq!(chain.lengths, vec![1.0, 2.0]);
+    }
+    
+    #[test]
+    fn blend_endpoints_match_fk_and_ik() {
+        let joints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)];
+        let mut chain = FabrikChain::new(joints, MotionHueristics::default());
+        chain.targets = vec![(3, Vec3::new(0.5, 2.0, 0.0))];
+        let blended = |blend: f32| {
+            let mut chain = chain.clone();
+            chain.fk_ik_blend = blend;
+            chain.solve_blended(10, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+            chain.joints
+        };
+        
+        let mut fk = chain.clone();
+        fk.recalculate_angles();
+        fk.apply_forward_kinematics();
+        let mut ik = chain.clone();
+        ik.solve(10, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+        
+        for (blended, expected) in [(blended(0.0), fk.joints), (blended(1.0), ik.joints)] {
+            for (joint, expected) in blended.iter().zip(expected.iter()) {
+                assert!(joint.distance(*expected) < 1e-5, "{blended:?} vs {expected:?}");
+            }
+        }
+    }
+    
+    #[test]
+    fn rotation_about_the_root_moves_the_tip_fastest() {
+        let mut chain = straight_chain(4);
+        let rotation = Quat::from_rotation_z(0.2);
+        for joint in chain.joints.iter_mut() {
+            *joint = rotation * *joint;
+        }
+        chain.prev_time = SystemTime::now() - Duration::from_millis(100);
+        chain.recalculate_segments();
+        
+        let velocities = &chain.segment_velocities;
+        assert_eq!(velocities.len(), 3);
+        assert!(velocities.windows(2).all(|pair| pair[1] > pair[0]), "{velocities:?}");
+        assert!((velocities[2] / velocities[0] - 5.0).abs() < 1e-3, "{velocities:?}");
+    }
+    
+    #[test]
+    fn subdividing_preserves_endpoints_and_length() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 2.0, 0.0)], MotionHueristics::default());
+        let pos = chain.closest_point(1, Vec3::new(3.0, 0.5, 0.0));
+        chain.insert_joint(1, pos);
+        
+        assert_eq!(chain.joints.len(), 4);
+        assert_eq!(chain.joints[0], Vec3::ZERO);
+        assert_eq!(chain.joints[3], Vec3::new(1.0, 2.0, 0.0));
+        assert!((chain.lengths.iter().sum::<f32>() - 3.0).abs() < 1e-5);
+        assert_eq!(chain.segment_transforms.len(), 3);
+        assert_eq!(chain.limb.as_ref().unwrap().joints.len(), 4);
+    }
+    
+    #[test]
+    fn conflicting_set_commands_resolve_in_order() {
+        let mut chain = straight_chain(3);
+        chain.apply_target_commands([
+            TargetCommand::Set(2, Vec3::Y),
+            TargetCommand::Set(1, Vec3::Z),
+            TargetCommand::Set(2, Vec3::NEG_Y),
+        ]);
+        assert_eq!(chain.targets, vec![(2, Vec3::NEG_Y), (1, Vec3::Z)]);
+        
+        chain.apply_target_commands([TargetCommand::ClearAll, TargetCommand::Set(2, Vec3::X)]);
+        assert_eq!(chain.targets, vec![(2, Vec3::X)]);
+    }
+    
+    #[test]
+    fn sub_epsilon_segment_keeps_its_previous_direction() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 2.0, 0.0)], MotionHueristics::default());
+        chain.epsilon = 0.01;
+        assert_eq!(chain.segment_directions[1], Vec3::Y);
+        
+        chain.joints[2] = chain.joints[1] + Vec3::new(0.004, 0.0, 0.004);
+        chain.recalculate_segments();
+        assert_eq!(chain.segment_directions[1], Vec3::Y);
+        assert!(chain.segment_directions[2].is_normalized());
+    }
+    
+    #[test]
+    fn half_passes_flag_only_the_joints_they_move() {
+        use ReachPass::{Backward, Forward, Untouched};
+        
+        let mut chain = straight_chain(4);
+        chain.targets = vec![(3, Vec3::new(2.0, 1.0, 0.0))];
+        chain.step_half_pass();
+        assert_eq!(chain.last_pass, Forward);
+        assert_eq!(chain.pass_touched, vec![Forward, Forward, Forward, Untouched]);
+        
+        chain.step_half_pass();
+        assert_eq!(chain.last_pass, Backward);
+        assert_eq!(chain.pass_touched, vec![Untouched, Backward, Backward, Backward]);
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn exported_motion_parses_back() {
+        let mut chain = straight_chain(3);
+        let first = chain.segment_transforms.clone();
+        chain.set_joint(2, Vec3::new(1.0, 1.0, 0.0), None);
+        let history = vec![first, chain.segment_transforms.clone()];
+        
+        let path = std::env::temp_dir().join("robot_arm_exported_motion.ron");
+        FabrikChain::export_transforms_ron(&history, &path).unwrap();
+        let parsed: Vec<Vec<Transform>> = ron::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(parsed, history);
+    }
+    
+    #[test]
+    fn folded_chains_report_self_intersections() {
+        let folded = FabrikChain::new(vec![Vec3::ZERO, Vec3::X * 2.0, Vec3::new(2.0, 1.0, 0.0), Vec3::new(0.0, 0.1, 0.0)], MotionHueristics::default());
+        assert_eq!(folded.self_intersections(0.1), vec![(0, 2)]);
+        
+        let open = FabrikChain::new(vec![Vec3::ZERO, Vec3::X * 2.0, Vec3::new(2.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0)], MotionHueristics::default());
+        assert!(open.self_intersections(0.1).is_empty());
+        assert!(straight_chain(5).self_intersections(0.1).is_empty());
+    }
+    
+    #[test]
+    fn collision_pass_separates_or_reports_failure() {
+        let joints = vec![Vec3::ZERO, Vec3::X * 2.0, Vec3::new(2.0, 1.0, 0.0), Vec3::new(0.0, 0.1, 0.0)];
+        let mut chain = FabrikChain::new(joints, MotionHueristics::default());
+        chain.collision_radius = 0.1;
+        let gap = |chain: &FabrikChain| segment_distance(chain.joints[0], chain.joints[1], chain.joints[2], chain.joints[3]);
+        let before = gap(&chain);
+        
+        let resolved = chain.resolve_self_collisions();
+        assert_eq!(resolved, chain.self_intersections(0.1).is_empty());
+        assert!(gap(&chain) > before);
+        assert_eq!(chain.joints[0], Vec3::ZERO);
+        for (pair, length) in chain.joints.windows(2).zip(chain.lengths.iter()) {
+            assert!((pair[0].distance(pair[1]) - length).abs() < 1e-4);
+        }
+    }
+    
+    #[test]
+    fn constant_velocity_has_no_acceleration_and_a_step_spikes() {
+        let mut chain = straight_chain(4);
+        for frame in 0..4 {
+            let angle = frame as f32 * 0.1;
+            advance_angles(&mut chain, vec![angle, angle], 1.0);
+        }
+        assert_eq!(chain.angular_accelerations.len(), 2);
+        assert!(chain.angular_accelerations.iter().all(|acceleration| acceleration.abs() < 1e-3), "{:?}", chain.angular_accelerations);
+        
+        advance_angles(&mut chain, vec![0.4, 1.3], 1.0);
+        assert!(chain.angular_accelerations[0].abs() < 1e-3, "{:?}", chain.angular_accelerations);
+        assert!(chain.angular_accelerations[1] > 0.8, "{:?}", chain.angular_accelerations);
+    }
+    
+    #[test]
+    fn frozen_and_pinned_joints_reduce_the_dof() {
+        let mut chain = straight_chain(5);
+        assert_eq!(chain.effective_dof(), 3);
+        
+        chain.set_frozen(2, true);
+        assert_eq!(chain.effective_dof(), 2);
+        chain.set_angle_limit(1, (1.0, 1.0));
+        assert_eq!(chain.effective_dof(), 1);
+        chain.set_frozen(2, false);
+        assert_eq!(chain.effective_dof(), 2);
+    }
+    
+    #[test]
+    fn fitting_to_its_own_projection_reproduces_the_pose() {
+        let joints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.5), Vec3::new(2.0, 1.5, 0.0)];
+        let mut chain = FabrikChain::new(joints.clone(), MotionHueristics::default());
+        let camera = Transform { translation: Vec3::new(0.5, 0.5, 10.0), rotation: Quat::IDENTITY, scale: Vec3::ONE };
+        
+        let keypoints = chain.project_to_camera(&camera);
+        chain.fit_to_2d(&keypoints, &camera);
+        for (joint, expected) in chain.joints.iter().zip(joints.iter()) {
+            assert!(joint.distance(*expected) < 1e-3, "{:?}", chain.joints);
+        }
+    }
+    
+    #[test]
+    fn real_and_fantasy_limbs_keep_separate_ground_locks() {
+        let mut chain = straight_chain(3);
+        chain.lock_ground = false;
+        chain.limb.as_mut().unwrap().lock_ground = true;
+        
+        solve_tip(&mut chain, Vec3::X * 5.0, 10);
+        let limb = chain.limb.as_mut().unwrap();
+        solve_tip(limb, Vec3::X * 5.0, 10);
+        
+        assert_eq!(limb.joints[0], Vec3::ZERO);
+        assert!(limb.lock_ground);
+        assert!(chain.joints[0].distance(Vec3::ZERO) > 1.0);
+        assert!(!chain.lock_ground);
+    }
+    
+    #[test]
+    fn smoothing_reduces_noise_and_keeps_the_endpoints() {
+        let noisy: Vec<Vec<Vec3>> = (0..20)
+            .map(|i| {
+                let noise = if i % 2 == 0 { 0.1 } else { -0.1 };
+                vec![Vec3::ZERO, Vec3::new(i as f32, noise, 0.0)]
+            })
+            .collect();
+        let mut smoothed = noisy.clone();
+        smooth_trajectory(&mut smoothed, 3);
+        
+        let variance = |poses: &[Vec<Vec3>]| poses.iter().map(|pose| pose[1].y * pose[1].y).sum::<f32>() / poses.len() as f32;
+        assert!(variance(&smoothed) < variance(&noisy) * 0.5);
+        assert_eq!(smoothed.first(), noisy.first());
+        assert_eq!(smoothed.last(), noisy.last());
+        assert!((smoothed[10][1].x - 10.0).abs() < 1e-5);
+    }
+    
+    #[test]
+    fn flip_prevention_keeps_the_bend_side() {
+        let joints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)];
+        let target = Vec3::new(0.4, -1.4, 0.0);
+        let solved = |prevent_flips: bool| {
+            let mut chain = FabrikChain::new(joints.clone(), MotionHueristics::default());
+            chain.prevent_flips = prevent_flips;
+            solve_tip(&mut chain, target, 10);
+            chain
+        };
+        
+        assert!(!solved(false).flipped_joints(&joints).is_empty());
+        let chain = solved(true);
+        assert!(chain.flipped_joints(&joints).is_empty(), "{:?}", chain.joints);
+        assert!(chain.get_ee().distance(target) < 1e-2);
+    }
+    
+    #[test]
+    fn relative_angles_are_identity_when_straight_and_match_a_bend() {
+        let straight = straight_chain(4).relative_angles();
+        assert_eq!(straight.len(), 2);
+        assert!(straight.iter().all(|rotation| rotation.angle_between(Quat::IDENTITY) < 1e-2));
+        
+        let bent = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, 1.0)], MotionHueristics::default()).relative_angles();
+        assert_eq!(bent.len(), 1);
+        assert!((bent[0].angle_between(Quat::IDENTITY) - std::f32::consts::FRAC_PI_2).abs() < 1e-3);
+    }
+    
+    #[test]
+    fn diff_is_zero_against_itself_and_the_shift_against_a_moved_copy() {
+        let chain = straight_chain(4);
+        assert!(chain.diff(&chain).iter().all(|delta| *delta == Vec3::ZERO));
+        
+        let mut shifted = chain.clone();
+        let shift = Vec3::new(0.5, -1.0, 2.0);
+        for joint in shifted.joints.iter_mut() {
+            *joint += shift;
+        }
+        assert_eq!(chain.diff(&shifted), vec![shift; 4]);
+    }
+    
+    #[test]
+    fn edits_past_an_anchor_leave_upstream_joints_alone() {
+        let mut chain = straight_chain(10);
+        chain.apply_target_commands([TargetCommand::Anchor(2, chain.joints[2]), TargetCommand::Set(4, Vec3::new(3.0, 1.0, 0.0))]);
+        let before = chain.joints.clone();
+        chain.solve(20, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+        
+        assert_eq!(chain.joints[..3], before[..3]);
+        assert!(chain.joints[4].distance(Vec3::new(3.0, 1.0, 0.0)) < before[4].distance(Vec3::new(3.0, 1.0, 0.0)));
+    }
+    
+    #[test]
+    fn targets_are_classified_by_tolerance() {
+        let mut chain = straight_chain(4);
+        chain.targets = vec![
+            (1, Vec3::new(1.0, 0.0005, 0.0)),
+            (2, Vec3::new(2.0, 0.5, 0.0)),
+            (3, Vec3::X * 3.0),
+        ];
+        assert_eq!(chain.targets_reached(), vec![(1, true), (2, false), (3, true)]);
+    }
+    
+    #[test]
+    fn cached_targets_warm_start_in_fewer_iterations() {
+        let (a, b) = (Vec3::new(1.5, 1.5, 0.0), Vec3::new(-1.0, 2.0, 0.0));
+        let mut chain = straight_chain(4);
+        chain.warm_start = true;
+        assert!(solve_tip(&mut chain, a, 10).converged);
+        assert!(solve_tip(&mut chain, b, 10).converged);
+        
+        let mut cold = chain.clone();
+        cold.warm_start_cache.clear();
+        let cold_iterations = solve_tip(&mut cold, a, 10).iterations;
+        let warm_iterations = solve_tip(&mut chain, a, 10).iterations;
+        assert!(warm_iterations < cold_iterations, "warm {warm_iterations}, cold {cold_iterations}");
+    }
+    
+    #[test]
+    fn arclength_points_run_from_root_to_tip() {
+        let chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 2.0, 0.0)], MotionHueristics::default());
+        assert_eq!(chain.point_at_arclength(0.0), Vec3::ZERO);
+        assert_eq!(chain.point_at_arclength(3.0), Vec3::new(1.0, 2.0, 0.0));
+        assert_eq!(chain.point_at_arclength(1.5), Vec3::new(1.0, 0.5, 0.0));
+        assert_eq!(chain.point_at_arclength(0.25), Vec3::new(0.25, 0.0, 0.0));
+        assert_eq!(chain.point_at_arclength(10.0), Vec3::new(1.0, 2.0, 0.0));
+    }
+    
+    #[test]
+    fn reset_after_adding_a_joint_keeps_vectors_in_sync() {
+        let mut chain = straight_chain(3);
+        chain.push_joint(1.0);
+        chain.joints[3] = Vec3::new(2.0, 1.0, 0.0);
+        chain.reset();
+        
+        assert_eq!(chain.joints.len(), 4);
+        assert_eq!(chain.joints[3], Vec3::X * 3.0);
+        assert_eq!(chain.lengths.len(), 3);
+        assert_eq!(chain.segment_masses.len(), 3);
+        assert_eq!(chain.segment_transforms.len(), 3);
+        assert_eq!(chain.limb.as_ref().unwrap().joints.len(), 4);
+        assert!(solve_tip(&mut chain, Vec3::new(1.0, 2.0, 0.0), 10).converged);
+    }
+    
+    #[cfg(debug_assertions)]
+    #[test]
+    fn traced_solve_matches_a_hand_verified_sequence() {
+        let mut chain = straight_chain(3);
+        chain.targets = vec![(2, Vec3::new(1.0, 1.0, 0.0))];
+        let trace = chain.solve_traced(5);
+        
+        let expected = [
+            vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0],
+            vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)],
+        ];
+        assert_eq!(trace.len(), expected.len(), "{trace:?}");
+        for (step, expected) in trace.iter().zip(expected.iter()) {
+            for (joint, expected) in step.iter().zip(expected) {
+                assert!(joint.distance(*expected) < 1e-5, "{trace:?}");
+            }
+        }
+        assert_eq!(chain.joints, trace[1]);
+    }
+    
+    #[test]
+    fn root_targets_follow_the_ground_lock() {
+        let root_target = Vec3::new(0.0, 0.0, 1.0);
+        
+        let mut locked = straight_chain(3);
+        locked.lock_ground = true;
+        locked.targets = vec![(0, root_target), (2, Vec3::new(1.0, 1.0, 0.0))];
+        locked.solve(10, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+        assert_eq!(locked.joints[0], Vec3::ZERO);
+        assert!(locked.joints[2].distance(Vec3::new(1.0, 1.0, 0.0)) < 1e-3, "{:?}", locked.joints);
+        
+        let mut unlocked = straight_chain(3);
+        unlocked.lock_ground = false;
+        unlocked.targets = vec![(0, root_target)];
+        unlocked.solve(10, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+        for (joint, expected) in unlocked.joints.iter().zip([Vec3::ZERO, Vec3::X, Vec3::X * 2.0]) {
+            assert!(joint.distance(expected + root_target) < 1e-4, "{:?}", unlocked.joints);
+        }
+    }
+    
+    #[test]
+    fn sanitize_repairs_a_malformed_chain() {
+        let mut chain = straight_chain(3);
+        chain.joints[1] = Vec3::new(f32::NAN, 1.0, 0.0);
+        chain.lengths = vec![1.0];
+        chain.segment_transforms.clear();
+        chain.angles = vec![0.0; 5];
+        chain.targets = vec![(7, Vec3::Y), (2, Vec3::new(f32::INFINITY, 0.0, 0.0))];
+        chain.limb = None;
+        chain.initial_state = None;
+        chain.sanitize();
+        
+        assert!(chain.joints.iter().all(|joint| joint.is_finite()));
+        assert_eq!(chain.joints[1], Vec3::Y);
+        assert_eq!(chain.lengths.len(), 2);
+        assert!((chain.lengths[1] - 5.0_f32.sqrt()).abs() < 1e-5);
+        assert_eq!(chain.segment_transforms.len(), 2);
+        assert_eq!(chain.angles.len(), 1);
+        assert!(chain.targets.is_empty());
+        assert_eq!(chain.limb.as_ref().unwrap().joints, chain.joints);
+        assert_eq!(chain.initial_state.as_ref().unwrap().joints, chain.joints);
+        
+        let report = solve_tip(&mut chain, Vec3::new(1.0, 1.5, 0.0), 10);
+        assert!(report.final_error.is_finite());
+        assert!(chain.joints.iter().all(|joint| joint.is_finite()));
+    }
+    
+    #[test]
+    fn hinged_joints_stay_in_their_plane() {
+        let mut chain = straight_chain(3);
+        chain.set_hinge(1, Some(Vec3::Z * 2.0));
+        assert_eq!(chain.hinge_axis(1), Some(Vec3::Z));
+        
+        for target in [Vec3::new(1.0, 0.5, 0.8), Vec3::new(0.5, -0.5, -1.2), Vec3::new(1.5, 1.0, 0.3)] {
+            solve_tip(&mut chain, target, 20);
+            let offset = chain.joints[2] - chain.joints[1];
+            assert!(offset.dot(Vec3::Z).abs() < 1e-4, "{:?}", chain.joints);
+            assert!((offset.length() - 1.0).abs() < 1e-4, "{:?}", chain.joints);
+        }
+    }
+    
+    #[test]
+    fn sensitivity_is_largest_near_the_root() {
+        let mut chain = straight_chain(4);
+        let sensitivity = chain.sensitivity(Vec3::Y * 0.1);
+        for (contribution, expected) in sensitivity.iter().zip([0.3, 0.2, 0.1, 0.0]) {
+            assert!((contribution - expected).abs() < 1e-5, "{sensitivity:?}");
+        }
+        
+        chain.set_frozen(1, true);
+        chain.set_hinge(2, Some(Vec3::Y));
+        assert_eq!(chain.sensitivity(Vec3::Y * 0.1)[1..], [0.0, 0.0, 0.0]);
+    }
+    
+    #[test]
+    fn clearing_constraints_keeps_the_pose() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)], MotionHueristics::default());
+        let joints = chain.joints.clone();
+        chain.set_frozen(1, true);
+        chain.set_hinge(2, Some(Vec3::Z));
+        chain.set_angle_limit(1, (0.5, 2.0));
+        chain.distance_constraints.push((0, 3, 1.5));
+        chain.motion_heuristics.anchor_points.push((2, joints[2], Quat::IDENTITY));
+        chain.motion_heuristics.ground_height = Some(-1.0);
+        chain.motion_heuristics.add_obstacle_plane(Vec3::X, Vec3::X * 3.0);
+        chain.stretch_tolerance = Some(0.1);
+        chain.length_budget = Some(2.0);
+        chain.avoid_self_collision = true;
+        chain.prevent_flips = true;
+        chain.pole_target = Some(Vec3::new(1.0, 0.0, 1.0));
+        chain.lock_ground = false;
+        chain.limb.as_mut().unwrap().set_frozen(2, true);
+        chain.clear_constraints();
+        
+        assert_eq!(chain.joints, joints);
+        assert!(chain.frozen.is_empty() && chain.hinge_axes.is_empty());
+        assert!(chain.distance_constraints.is_empty() && chain.angle_limits.is_empty());
+        assert!(chain.motion_heuristics.anchor_points.is_empty());
+        assert_eq!(chain.motion_heuristics.ground_height, None);
+        assert!(chain.motion_heuristics.obstacle_planes.is_empty());
+        assert_eq!((chain.stretch_tolerance, chain.length_budget), (None, None));
+        assert!(!chain.avoid_self_collision && !chain.prevent_flips && chain.lock_ground);
+        assert_eq!(chain.pole_target, None);
+        assert!(chain.limb.as_ref().unwrap().frozen.is_empty());
+    }
+    
+    #[test]
+    fn iteration_hook_fires_once_per_pass() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut chain = straight_chain(3);
+        let recorded = calls.clone();
+        chain.on_iteration = Some(IterationHook::new(move |_, iteration| recorded.lock().unwrap().push(iteration)));
+        
+        let report = solve_tip(&mut chain, Vec3::new(0.0, 5.0, 0.0), 10);
+        assert_eq!(report.iterations, 10);
+        assert_eq!(*calls.lock().unwrap(), (0..10).collect::<Vec<_>>());
+        
+        calls.lock().unwrap().clear();
+        let report = solve_tip(&mut chain, Vec3::new(1.0, 1.0, 0.0), 10);
+        assert!(report.iterations < 10);
+        assert_eq!(*calls.lock().unwrap(), (0..report.iterations).collect::<Vec<_>>());
+    }
+    
+    #[test]
+    fn equalizing_spreads_angular_velocity() {
+        let velocity_variance = |equalize_velocity: bool| {
+            let joints = vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0), Vec3::new(2.0, 2.0, 0.0)];
+            let mut chain = FabrikChain::new(joints, MotionHueristics::default());
+            chain.equalize_velocity = equalize_velocity;
+            solve_tip(&mut chain, Vec3::new(-1.0, 1.5, 0.0), 10);
+            chain.recalculate_angles();
+            chain.prev_time = SystemTime::now() - Duration::from_secs(1);
+            chain.recalculate_segments();
+            
+            let velocities = &chain.angular_velocities;
+            let mean = velocities.iter().sum::<f32>() / velocities.len() as f32;
+            velocities.iter().map(|velocity| (velocity - mean).powi(2)).sum::<f32>() / velocities.len() as f32
+        };
+        let (plain, equalized) = (velocity_variance(false), velocity_variance(true));
+        assert!(equalized < plain, "equalized {equalized}, plain {plain}");
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bincode_round_trip_rebuilds_the_chain() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.5)], MotionHueristics::default());
+        chain.set_frozen(1, true);
+        chain.set_hinge(2, Some(Vec3::Z));
+        chain.set_angle_limit(2, (0.25, 2.5));
+        chain.distance_constraints.push((0, 3, 2.0));
+        chain.segment_masses[1] = 2.5;
+        chain.targets = vec![(3, Vec3::new(1.5, 1.5, 0.0))];
+        chain.motion_heuristics.anchor_points.push((2, Vec3::new(1.0, 1.0, 0.0), Quat::IDENTITY));
+        chain.motion_heuristics.ground_height = Some(-0.5);
+        chain.motion_heuristics.add_obstacle_plane(Vec3::X, Vec3::X * 4.0);
+        chain.stretch_tolerance = Some(0.2);
+        chain.tool_offset = Vec3::Y * 0.3;
+        chain.lock_ground = false;
+        chain.up_axis = Vec3::Z;
+        chain.set_home();
+        chain.tolerance = 1e-2;
+        chain.epsilon = 1e-5;
+        chain.solver = SolverKind::Ccd;
+        chain.prediction_time = 0.1;
+        chain.fk_ik_blend = 0.5;
+        chain.pole_target = Some(Vec3::new(1.0, 0.0, 1.0));
+        chain.tip_orientation = Some(Quat::from_rotation_z(0.5));
+        chain.tool_roll = 0.3;
+        chain.collision_radius = 0.2;
+        chain.avoid_self_collision = true;
+        chain.prevent_flips = true;
+        chain.equalize_velocity = true;
+        chain.warm_start = true;
+        chain.limb.as_mut().unwrap().joints[3] = Vec3::new(2.0, 1.0, 0.0);
+        
+        let path = std::env::temp_dir().join("robot_arm_chain.bin");
+        chain.save_bincode(&path).unwrap();
+        let loaded = FabrikChain::load_bincode(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        assert_eq!(loaded.joints, chain.joints);
+        assert_eq!(loaded.lengths, chain.lengths);
+        assert_eq!(loaded.frozen, chain.frozen);
+        assert_eq!(loaded.hinge_axes, chain.hinge_axes);
+        assert_eq!(loaded.angle_limits, chain.angle_limits);
+        assert_eq!(loaded.distance_constraints, chain.distance_constraints);
+        assert_eq!(loaded.segment_masses, chain.segment_masses);
+        assert_eq!(loaded.targets, chain.targets);
+        assert_eq!(loaded.motion_heuristics.anchor_points, chain.motion_heuristics.anchor_points);
+        assert_eq!(loaded.motion_heuristics.ground_height, chain.motion_heuristics.ground_height);
+        assert_eq!(loaded.motion_heuristics.obstacle_planes, chain.motion_heuristics.obstacle_planes);
+        assert_eq!((loaded.stretch_tolerance, loaded.tool_offset, loaded.lock_ground), (chain.stretch_tolerance, chain.tool_offset, chain.lock_ground));
+        assert_eq!((loaded.up_axis, loaded.tolerance, loaded.epsilon, loaded.solver), (chain.up_axis, chain.tolerance, chain.epsilon, chain.solver));
+        assert_eq!((loaded.prediction_time, loaded.fk_ik_blend, loaded.pole_target), (chain.prediction_time, chain.fk_ik_blend, chain.pole_target));
+        assert_eq!((loaded.tip_orientation, loaded.tool_roll, loaded.collision_radius), (chain.tip_orientation, chain.tool_roll, chain.collision_radius));
+        assert!(loaded.avoid_self_collision && loaded.prevent_flips && loaded.equalize_velocity && loaded.warm_start);
+        assert_eq!(loaded.limb.as_ref().unwrap().joints, chain.limb.as_ref().unwrap().joints);
+        assert_eq!(loaded.initial_state.as_ref().unwrap().tolerance, chain.initial_state.as_ref().unwrap().tolerance);
+        assert_ne!(loaded.initial_state.as_ref().unwrap().tolerance, loaded.tolerance);
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn bincode_loads_keep_a_matching_limb() {
+        let path = std::env::temp_dir().join("robot_arm_limbless_chain.bin");
+        let mut chain = straight_chain(3);
+        chain.limb = None;
+        chain.save_bincode(&path).unwrap();
+        let loaded = FabrikChain::load_bincode(&path).unwrap();
+        assert_eq!(loaded.limb.as_ref().unwrap().joints, chain.joints);
+        
+        let mut chain = straight_chain(4);
+        chain.limb.as_mut().unwrap().pop_joint();
+        chain.save_bincode(&path).unwrap();
+        let loaded = FabrikChain::load_bincode(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+    
+    #[test]
+    fn tool_offset_extends_the_end_effector() {
+        let mut chain = straight_chain(3);
+        chain.tool_offset = Vec3::Y * 0.5;
+        assert!(chain.get_ee().distance(Vec3::X * 2.5) < 1e-5, "{}", chain.get_ee());
+        
+        let target = Vec3::new(1.0, 1.5, 0.0);
+        let report = solve_tip(&mut chain, target, 30);
+        let expected = chain.joints[2] + chain.get_ee_orientation() * chain.tool_offset;
+        assert!(chain.get_ee().distance(expected) < 1e-5);
+        assert!(chain.get_ee().distance(target) < 2e-3, "{report:?}");
+        assert!(chain.joints[2].distance(target) > 0.4);
+    }
+    
+    #[test]
+    fn braced_joints_converge_to_their_separation() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)], MotionHueristics::default());
+        chain.distance_constraints.push((0, 2, 1.0));
+        chain.solve(30, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+        
+        assert!((chain.joints[0].distance(chain.joints[2]) - 1.0).abs() < 1e-3, "{:?}", chain.joints);
+        assert_eq!(chain.joints[0], Vec3::ZERO);
+        for (segment, length) in chain.joints.windows(2).zip(chain.lengths.iter()) {
+            assert!((segment[0].distance(segment[1]) - length).abs() < 1e-3, "{:?}", chain.joints);
+        }
+    }
+    
+    #[test]
+    fn elbow_limits_hold_for_a_target_behind_the_base() {
+        let target = Vec3::new(-1.0, 0.5, 0.0);
+        let elbow = |chain: &FabrikChain| (chain.joints[0] - chain.joints[1]).angle_between(chain.joints[2] - chain.joints[1]);
+        
+        let mut free = straight_chain(3);
+        solve_tip(&mut free, target, 20);
+        assert!(elbow(&free) < 2.0, "{:?}", free.joints);
+        
+        let mut limited = straight_chain(3);
+        limited.set_angle_limit(1, (2.0, std::f32::consts::PI));
+        assert_eq!(limited.angle_limit(1), (2.0, std::f32::consts::PI));
+        assert_eq!(limited.angle_limit(0), (0.0, std::f32::consts::PI));
+        let report = solve_tip(&mut limited, target, 20);
+        assert!(elbow(&limited) >= 2.0 - 1e-4, "{:?}", limited.joints);
+        assert!(!report.converged);
+    }
+    
+    #[test]
+    fn reachable_targets_exit_early() {
+        let mut chain = straight_chain(4);
+        assert_eq!(chain.tolerance, 1e-3);
+        let report = solve_tip(&mut chain, Vec3::new(1.0, 2.0, 0.0), 10);
+        assert!(report.converged);
+        assert!(report.iterations < 10, "{report:?}");
+        assert!(chain.get_ee().distance(Vec3::new(1.0, 2.0, 0.0)) <= chain.tolerance);
+    }
+    
+    #[test]
+    fn severe_divergence_restores_a_bounded_pose() {
+        let mut chain = straight_chain(3);
+        chain.recalculate_angles();
+        chain.joints[1] = Vec3::new(50.0, 40.0, 0.0);
+        chain.joints[2] = Vec3::new(100.0, -30.0, 20.0);
+        chain.targets = vec![(2, Vec3::new(10.0, 10.0, 0.0))];
+        
+        let mut kinematics_mode = KinematicsMode::ForwardKinematics;
+        let report = chain.solve(10, PoseDiscrepancy::SevereDivergence, &mut kinematics_mode);
+        assert_eq!(kinematics_mode, KinematicsMode::InverseKinematics);
+        assert!(chain.joints.iter().all(|joint| joint.is_finite()));
+        assert!(chain.joints.iter().all(|joint| joint.length() <= 2.0 + 1e-4), "{:?}", chain.joints);
+        assert!(!report.reachable);
+        assert_eq!(chain.angular_velocities.len(), 1);
+    }
+    
+    #[test]
+    fn compensation_keeps_joints_above_the_ground() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::Y, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)], MotionHueristics::default());
+        chain.motion_heuristics.ground_height = Some(0.0);
+        chain.targets = vec![(3, Vec3::new(1.5, -1.0, 0.5))];
+        chain.solve(20, PoseDiscrepancy::EnvironmentalCompensation, &mut KinematicsMode::default());
+        assert!(chain.joints.iter().all(|joint| joint.y >= 0.0), "{:?}", chain.joints);
+        
+        let mut uncompensated = FabrikChain::new(vec![Vec3::ZERO, Vec3::Y, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)], MotionHueristics::default());
+        uncompensated.motion_heuristics.ground_height = Some(0.0);
+        solve_tip(&mut uncompensated, Vec3::new(1.5, -1.0, 0.5), 20);
+        assert!(uncompensated.joints[3].y < 0.0, "{:?}", uncompensated.joints);
+    }
+    
+    #[test]
+    fn two_joint_chains_have_one_length() {
+        let chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::new(0.0, 2.0, 0.0)], MotionHueristics::default());
+        assert_eq!(chain.lengths, vec![2.0]);
+        assert_eq!(chain.segment_transforms.len(), 1);
+    }
+    
+    #[test]
+    #[should_panic(expected = "at least 2 joints")]
+    fn single_joint_chains_are_rejected() {
+        FabrikChain::new(vec![Vec3::ZERO], MotionHueristics::default());
+    }
+    
+    #[test]
+    #[should_panic(expected = "at least 2 joints")]
+    fn empty_chains_are_rejected() {
+        FabrikChain::new(Vec::new(), MotionHueristics::default());
+    }
+    
+    #[test]
+    fn reach_passes_preserve_segment_lengths() {
+        let mut chain = straight_chain(5);
+        chain.joints[4] = Vec3::new(3.0, 1.5, -0.5);
+        chain.fwd_reach();
+        chain.joints[0] = Vec3::ZERO;
+        chain.bwd_reach();
+        
+        for (segment, length) in chain.joints.windows(2).zip(chain.lengths.iter()) {
+            assert!((segment[0].distance(segment[1]) - length).abs() < 1e-4, "{:?}", chain.joints);
+        }
+        assert_eq!(chain.joints[0], Vec3::ZERO);
+    }
+    
+    #[test]
+    fn right_angle_bends_measure_half_pi() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(1.0, 2.0, 0.0)], MotionHueristics::default());
+        chain.recalculate_angles();
+        assert_eq!(chain.angles.len(), 2);
+        assert!((chain.angles[0] - std::f32::consts::FRAC_PI_2).abs() < 1e-5, "{:?}", chain.angles);
+        assert!((chain.angles[1] - std::f32::consts::PI).abs() < 1e-3, "{:?}", chain.angles);
+    }
+    
+    #[test]
+    fn fabrik_and_ccd_reach_the_same_target() {
+        let target = Vec3::new(1.0, 1.5, 0.0);
+        for solver in [SolverKind::Fabrik, SolverKind::Ccd] {
+            let mut chain = straight_chain(4);
+            chain.solver = solver;
+            solve_tip(&mut chain, target, 50);
+            assert!(chain.get_ee().distance(target) < 1e-2, "{solver:?} {:?}", chain.joints);
+            assert_eq!(chain.joints[0], Vec3::ZERO);
+        }
+    }
+    
+    #[test]
+    fn custom_solvers_replace_the_builtin_passes() {
+        struct NoOp(Arc<Mutex<usize>>);
+        impl Solver for NoOp {
+            fn solve(&self, _: &mut FabrikChain, _: &[(usize, Vec3)], _: &[Vec3], _: usize) -> usize {
+                *self.0.lock().unwrap() += 1;
+                0
+            }
+        }
+        
+        let calls = Arc::new(Mutex::new(0));
+        let mut chain = straight_chain(3);
+        chain.custom_solver = Some(CustomSolver::new(NoOp(calls.clone())));
+        let report = solve_tip(&mut chain, Vec3::new(1.0, 1.0, 0.0), 10);
+        assert_eq!(*calls.lock().unwrap(), 1);
+        assert_eq!(report.iterations, 0);
+        assert_eq!(chain.joints, vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0]);
+    }
+    
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip_keeps_the_pose() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.5, 0.0)], MotionHueristics::default());
+        chain.recalculate_angles();
+        let json = serde_json::to_string(&chain).unwrap();
+        let loaded: FabrikChain = serde_json::from_str(&json).unwrap();
+        
+        assert_eq!(loaded.joints, chain.joints);
+        assert_eq!(loaded.lengths, chain.lengths);
+        assert_eq!(loaded.angles, chain.angles);
+        assert_eq!(loaded.limb.as_ref().unwrap().joints, chain.limb.as_ref().unwrap().joints);
+        assert_eq!(loaded.initial_state.as_ref().unwrap().joints, chain.initial_state.as_ref().unwrap().joints);
+        assert!(loaded.prev_time.elapsed().unwrap() < Duration::from_secs(60));
+    }
+    
+    #[test]
+    fn unreachable_targets_are_flagged() {
+        let mut chain = straight_chain(3);
+        let report = solve_tip(&mut chain, Vec3::new(1.0, 1.0, 0.0), 10);
+        assert!(report.reachable && report.converged, "{report:?}");
+        
+        let mut chain = straight_chain(3);
+        let report = solve_tip(&mut chain, Vec3::new(0.0, 3.0, 0.0), 10);
+        assert!(!report.reachable && !report.converged, "{report:?}");
+        assert!((report.final_error - 1.0).abs() < 1e-3, "{report:?}");
+    }
+    
+    #[test]
+    fn opposite_poles_bend_the_elbow_to_opposite_sides() {
+        let target = Vec3::new(1.5, 0.5, 0.0);
+        let elbow_side = |pole: Vec3| {
+            let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)], MotionHueristics::default());
+            chain.pole_target = Some(pole);
+            let report = solve_tip(&mut chain, target, 20);
+            assert!(report.converged, "{report:?}");
+            
+            let axis = (chain.joints[2] - chain.joints[0]).normalize();
+            let offset = chain.joints[1] - chain.joints[0];
+            (offset - axis * offset.dot(axis)).dot(Vec3::Z)
+        };
+        let (above, below) = (elbow_side(Vec3::new(0.5, 0.2, 2.0)), elbow_side(Vec3::new(0.5, 0.2, -2.0)));
+        assert!(above > 0.5 && below < -0.5, "above {above}, below {below}");
+    }
+    
+    #[test]
+    fn solve_error_decreases_each_iteration() {
+        let mut chain = straight_chain(4);
+        let target = Vec3::new(-1.5, 1.0, 0.5);
+        let report = solve_tip(&mut chain, target, 20);
+        assert!(report.converged, "{report:?}");
+        assert_eq!(report.error_history.len(), report.iterations);
+        assert!(report.error_history.len() >= 3, "{report:?}");
+        assert!(report.error_history.windows(2).all(|pair| pair[1] < pair[0]), "{report:?}");
+        assert_eq!(report.final_error, *report.error_history.last().unwrap());
+        assert!(report.max_joint_delta > 0.0);
+    }
+    
+    #[test]
+    fn accelerations_track_a_known_angle_sequence() {
+        let mut chain = straight_chain(4);
+        advance_angles(&mut chain, vec![0.0, 1.0], 0.5);
+        advance_angles(&mut chain, vec![0.1, 0.8], 0.5);
+        assert!(chain.angular_accelerations.is_empty());
+        
+        let frames = [
+            (vec![0.3, 0.7], [0.4, -0.2], [0.4, 0.4]),
+            (vec![0.6, 0.65], [0.6, -0.1], [0.4, 0.2]),
+            (vec![0.6, 0.65], [0.0, 0.0], [-1.2, 0.2]),
+        ];
+        for (angles, velocities, accelerations) in frames {
+            advance_angles(&mut chain, angles, 0.5);
+            for (actual, expected) in chain.angular_velocities.iter().zip(velocities) {
+                assert!((actual - expected).abs() < 1e-2, "{:?}", chain.angular_velocities);
+            }
+            assert_eq!(chain.angular_accelerations.len(), 2);
+            for (actual, expected) in chain.angular_accelerations.iter().zip(accelerations) {
+                assert!((actual - expected).abs() < 1e-2, "{:?}", chain.angular_accelerations);
+            }
+        }
+    }
+    
+    #[test]
+    fn velocities_are_in_radians_per_second() {
+        let mut chain = straight_chain(3);
+        chain.angles = vec![0.5];
+        advance_angles(&mut chain, vec![0.8], 0.25);
+        assert!((chain.angular_velocities[0] - 1.2).abs() < 1e-2, "{:?}", chain.angular_velocities);
+        
+        chain.prev_angles = vec![0.8];
+        chain.angles = vec![1.8];
+        chain.prev_time = SystemTime::now() + Duration::from_secs(1);
+        chain.recalculate_segments();
+        assert_eq!(chain.angular_velocities, vec![0.0]);
+    }
+    
+    #[test]
+    fn z_up_segments_have_unit_rotations() {
+        let mut chain = straight_chain(4);
+        chain.up_axis = Vec3::Z;
+        chain.recalculate_segments();
+        assert_eq!(chain.segment_transforms.len(), 3);
+        for transform in &chain.segment_transforms {
+            assert!(transform.rotation.is_finite() && transform.rotation.is_normalized(), "{:?}", transform.rotation);
+            assert!((transform.rotation * Vec3::Y).distance(Vec3::X) < 1e-5, "{:?}", transform.rotation);
+        }
+    }
+    
+    #[test]
+    fn vertical_segments_have_unit_rotations() {
+        for tip in [Vec3::Y, Vec3::NEG_Y * 2.0] {
+            let chain = FabrikChain::new(vec![Vec3::ZERO, tip], MotionHueristics::default());
+            let rotation = chain.segment_transforms[0].rotation;
+            assert!(rotation.is_finite() && rotation.is_normalized(), "{rotation:?}");
+            assert!((rotation * Vec3::Y).distance(tip.normalize()) < 1e-5, "{rotation:?}");
+        }
+    }
+    
+    #[test]
+    fn straight_angles_lay_joints_on_a_line() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.5, 0.0), Vec3::new(2.0, 1.5, 0.0)], MotionHueristics::default());
+        chain.recalculate_angles();
+        chain.angles = vec![std::f32::consts::PI; 2];
+        chain.apply_forward_kinematics();
+        
+        let expected = [Vec3::ZERO, Vec3::X, Vec3::X * 2.5, Vec3::X * 3.5];
+        for (joint, expected) in chain.joints.iter().zip(expected) {
+            assert!(joint.distance(expected) < 1e-5, "{:?}", chain.joints);
+        }
+    }
+    
+    #[test]
+    fn end_effector_orientation_follows_the_last_bone() {
+        for joints in [
+            vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)],
+            vec![Vec3::ZERO, Vec3::Y, Vec3::new(0.0, 1.0, 2.0)],
+            vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 0.0, -1.0), Vec3::new(3.0, 0.0, -1.0)],
+        ] {
+            let chain = FabrikChain::new(joints, MotionHueristics::default());
+            let tip = chain.joints.len() - 1;
+            let bone = (chain.joints[tip] - chain.joints[tip - 1]).normalize();
+            let orientation = chain.get_ee_orientation();
+            assert!(orientation.is_normalized());
+            assert!((orientation * Vec3::Y).distance(bone) < 1e-5, "{orientation:?} {bone}");
+            
+            let pose = chain.get_ee_pose();
+            assert_eq!(pose.translation, chain.joints[tip]);
+            assert_eq!(pose.rotation, orientation);
+        }
+    }
+    
+    #[test]
+    fn tip_orientation_targets_are_met() {
+        for (rotation, target) in [
+            (Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2), Vec3::new(2.0, 1.0, 0.0)),
+            (Quat::from_rotation_x(std::f32::consts::FRAC_PI_2), Vec3::new(1.0, 1.0, 1.0)),
+        ] {
+            let mut chain = straight_chain(4);
+            chain.tip_orientation = Some(rotation);
+            solve_tip(&mut chain, target, 20);
+            
+            assert!(chain.get_ee().distance(target) < 1e-2, "{:?}", chain.joints);
+            let error = chain.get_ee_orientation().angle_between(rotation);
+            assert!(error < 3f32.to_radians(), "{error} rad off, joints {:?}", chain.joints);
+        }
+    }
+    
+    #[test]
+    fn clearing_the_tip_orientation_drops_the_tool_roll() {
+        let mut chain = straight_chain(4);
+        chain.tool_offset = Vec3::X * 0.3;
+        chain.tip_orientation = Some(Quat::from_rotation_z(-std::f32::consts::FRAC_PI_2) * Quat::from_rotation_y(0.7));
+        solve_tip(&mut chain, Vec3::new(2.0, 1.0, 0.0), 20);
+        assert!(chain.tool_roll.abs() > 0.1, "{}", chain.tool_roll);
+        
+        chain.tip_orientation = None;
+        solve_tip(&mut chain, Vec3::new(1.5, 1.5, 0.0), 20);
+        let tip = chain.joints.len() - 1;
+        let bone = chain.joints[tip] - chain.joints[tip - 1];
+        let orientation = chain.get_ee_orientation();
+        assert_eq!(chain.tool_roll, 0.0);
+        assert!(orientation.angle_between(segment_rotation(chain.joints[tip], chain.joints[tip - 1], chain.up_axis)) < 1e-4);
+        assert!((orientation * Vec3::Y).distance(bone.normalize()) < 1e-4);
+        assert!(chain.get_ee().distance(chain.joints[tip] + orientation * chain.tool_offset) < 1e-5);
+    }
+}
@@ -1,25 +1,143 @@
-use crate::ik::{FabrikChain, KinematicsMode, MotionHueristics, PoseDiscrepancy};
+use crate::camera::{OrbitCamera, ResetCamera};
+use crate::config::LoadConfig;
+use crate::pose_library::{POSE_LIBRARY_FILE, PoseLibrary, PoseRecall};
+use crate::session::{LoadSession, SESSION_FILE, SESSION_HISTORY_CAP, Session};
+use crate::templates::{ChainTemplates, LoadTemplate};
+use crate::theme::{THEME_FILE, Theme};
 
-use bevy::{light::PointLightShadowMap, prelude::*};
-use bevy_egui::{EguiContexts, EguiPostUpdateSet, EguiPreUpdateSet, egui::Window};
-use bevy_transform_gizmo::{TransformGizmoInteraction, TransformGizmoPlugin};
-use egui_plot::{Line, Plot, PlotPoints};
+use std::{collections::VecDeque, time::{Duration, Instant}};
+
+use bevy::{light::PointLightShadowMap, picking::mesh_picking::MeshPickingPlugin, prelude::*};
+use bevy_egui::{EguiContexts, EguiPostUpdateSet, EguiPreUpdateSet, egui::{Button, Color32, ComboBox, DragValue, ProgressBar, Slider, Ui, Window}};
+use egui_plot::{Bar, BarChart, Line, Plot, PlotPoints};
+use robot_arm::ik::{FabrikChain, KinematicsMode, MotionHueristics, PoseDiscrepancy, ReachPass, SolveReport, SolverKind, TargetCommand};
 use strum::IntoEnumIterator;
 
-mod ik;
+mod bench;
+mod camera;
+mod config;
+#[cfg(feature = "hot-reload")]
+mod hot_reload;
+mod plot_export;
+mod pose_library;
+mod session;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod templates;
+mod theme;
 
 #[derive(Resource)]
 pub struct UiState {
     lock_ground: bool,
-    kinematics_mode: KinematicsMode
+    lock_fantasy_ground: bool,
+    kinematics_mode: KinematicsMode,
+    pose_discrepancy: PoseDiscrepancy,
+    tip_load: f32,
+    show_rest_deviation: bool,
+    drag_smoothing: f32,
+    animate: bool,
+    animation_speed: f32,
+    round_trip_residual: Option<f32>,
+    show_frames: bool,
+    subdivide: bool,
+    show_passes: bool,
+    fixed_solve: bool,
+    forward_kinematics: bool,
+    solve_hz: f64,
+    pose_name: String,
+    interpolate_recall: bool,
+    snap_grid: Option<f32>,
+    chain_template: Option<String>,
+    theme: Theme,
+    anchor_on_click: bool,
+    downsample_plots: bool,
+    brace: (usize, usize),
+    config_error: Option<String>,
+    ee_target: Vec3,
 }
 
 impl Default for UiState {
     fn default() -> Self {
-        Self { lock_ground: true, kinematics_mode: KinematicsMode::InverseKinematics }
+        Self {
+            lock_ground: true,
+            lock_fantasy_ground: true,
+            kinematics_mode: KinematicsMode::InverseKinematics,
+            pose_discrepancy: PoseDiscrepancy::default(),
+            tip_load: 1.0,
+            show_rest_deviation: false,
+            drag_smoothing: 0.0,
+            animate: false,
+            animation_speed: 6.0,
+            round_trip_residual: None,
+            show_frames: false,
+            subdivide: false,
+            show_passes: false,
+            fixed_solve: false,
+            forward_kinematics: false,
+            solve_hz: 60.0,
+            pose_name: String::new(),
+            interpolate_recall: true,
+            snap_grid: None,
+            chain_template: None,
+            theme: Theme::default(),
+            anchor_on_click: false,
+            downsample_plots: true,
+            brace: (0, 2),
+            config_error: None,
+            ee_target: Vec3::ZERO,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Paused(bool);
+
+#[derive(Resource, Default)]
+pub struct LastSolve(Option<SolveReport>);
+
+#[derive(Resource, Default)]
+pub struct AnimationGoal(Option<Vec<Vec3>>);
+
+#[derive(Resource, Default)]
+pub struct PendingSolve(bool);
+
+#[derive(Resource, Default)]
+pub struct PreviousSolvedPose {
+    joints: Vec<Vec3>,
+    segments: Vec<Transform>,
+}
+
+impl LastSolve {
+    pub fn converged(&self) -> bool {
+        self.0.as_ref().is_some_and(|report| report.converged)
+    }
+    
+    pub fn reachable(&self) -> bool {
+        self.0.as_ref().is_none_or(|report| report.reachable)
     }
 }
 
+#[derive(Resource, Default)]
+pub struct SolveBudget {
+    current: Duration,
+    history: VecDeque<f32>,
+}
+
+#[derive(Resource, Default)]
+pub struct TargetQueue(Vec<TargetCommand>);
+
+#[derive(Resource, Default)]
+pub struct DesiredTargets(Vec<(usize, Vec3)>);
+
+const SEGMENT_RADIUS: f32 = 0.15;
+const FLOOR_HEIGHT: f32 = -0.3;
+const DRAG_SETTLE_DISTANCE: f32 = 1e-3;
+const ROUND_TRIP_TOLERANCE: f32 = 1e-3;
+const PLOT_BUCKETS: usize = 500;
+const SOLVE_BUDGET_WINDOW: usize = 120;
+const SENSITIVITY_DELTA: f32 = 0.05;
+const DEFAULT_SEGMENT_LENGTH: f32 = 1.0;
+
 #[derive(Component)]
 pub struct LimbData(FabrikChain);
 
@@ -39,16 +157,47 @@ impl LimbData {
     }
 }
 
+#[derive(Resource)]
+struct LimbAssets {
+    control_ball_mesh: Handle<Mesh>,
+    ball_mesh: Handle<Mesh>,
+    fantasy_ball_mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+    fantasy_material: Handle<StandardMaterial>,
+    transculent_material: Handle<StandardMaterial>,
+    collision_material: Handle<StandardMaterial>,
+    target_mesh: Handle<Mesh>,
+    target_material: Handle<StandardMaterial>,
+}
+
 #[derive(Component, Default)]
 pub struct VelocityDisplay(Vec<Vec<f32>>);
 
+#[derive(Component, Default)]
+pub struct SegmentVelocityDisplay(Vec<Vec<f32>>);
+
+#[derive(Component, Default)]
+pub struct AccelerationDisplay(Vec<Vec<f32>>);
+
+#[derive(Component, Default)]
+pub struct TransformHistory(VecDeque<Vec<Transform>>);
+
 fn main() {
+    if std::env::args().any(|arg| arg == "--bench") {
+        let chain = FabrikChain::new(default_joints(), MotionHueristics::default());
+        println!("{}", bench::run(&chain, 0, 1000, 10));
+        println!("\nRepeating targets, cold:\n{}", bench::run_repeating(&chain, 0, 1000, 10, false));
+        println!("\nRepeating targets, warm-start:\n{}", bench::run_repeating(&chain, 0, 1000, 10, true));
+        return;
+    }
+    
     let window = bevy::prelude::Window {
         title: "Robot Arm".to_string(),
         ..default()
     };
     
-    App::new()
+    let mut app = App::new();
+    app
         // .add_sub_state::<LimbState>()
         .add_plugins(
             DefaultPlugins.set(WindowPlugin {
@@ -56,42 +205,162 @@ fn main() {
                 ..default()
             })
         )
-        // .add_plugins(DefaultPickingPlugins)
+        .add_plugins(MeshPickingPlugin)
         .add_plugins(bevy_egui::EguiPlugin::default())
-        // .add_plugins(TransformGizmoPlugin::default())
         .add_message::<SyncTransform>()
         .add_message::<RecomputeLimb>()
         .add_message::<MoveLimb>()
+        .add_message::<EditJointCount>()
+        .add_message::<RebuildSegmentMesh>()
+        .add_message::<ResetCamera>()
         .add_message::<GizmoUpdate>()
+        .add_message::<LoadTemplate>()
+        .add_message::<LoadSession>()
+        .add_message::<LoadConfig>()
         // .insert_resource(Msaa::Sample4)
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(PointLightShadowMap { size: 8192 })
         .init_state::<LimbState>()
         .init_resource::<UiState>()
+        .init_resource::<Paused>()
+        .init_resource::<DesiredTargets>()
+        .init_resource::<TargetQueue>()
+        .init_resource::<PoseRecall>()
+        .init_resource::<LastSolve>()
+        .init_resource::<AnimationGoal>()
+        .init_resource::<PendingSolve>()
+        .init_resource::<PreviousSolvedPose>()
+        .init_resource::<SolveBudget>()
         // .init_resource::<State<LimbState>>()
-        .add_systems(Startup, setup)
+        .configure_sets(
+            Update, 
+            RespawnLimb
+                .before(sync_ball_transform)
+                .before(sync_ctrl_ball_transform)
+                .before(sync_segment_transform)
+        )
+        .add_systems(Startup, (setup, pose_library::load_pose_library, templates::discover_chain_templates, theme::load_theme))
+        .add_observer(subdivide_segment)
+        .add_observer(anchor_to_surface)
+        .add_observer(grab_control_ball)
+        .add_observer(drag_control_ball)
+        .add_observer(release_control_ball)
         .add_systems(
             Update, 
             display_ui
                 .after(EguiPreUpdateSet::InitContexts)
                 .before(EguiPostUpdateSet::ProcessOutput)
         )
+        .add_systems(
+            Update, 
+            theme::apply_theme.after(EguiPreUpdateSet::InitContexts)
+        )
+        .add_systems(
+            Update, 
+            camera::camera_controls.after(EguiPreUpdateSet::InitContexts)
+        )
+        .add_systems(First, roll_solve_budget)
+        .add_systems(Update, toggle_pause)
+        .add_systems(
+            Update, 
+            pose_library::step_pose_recall.run_if(not_paused)
+        )
+        .add_systems(
+            Update, 
+            ease_targets
+                .run_if(not_paused)
+                .before(move_limb)
+        )
         .add_systems(
             Update, 
             move_limb
+                .run_if(not_paused)
                 .run_if((on_message::<GizmoUpdate>).or(on_message::<MoveLimb>))
                 .before(recompute_limb)
         )          
+        .add_systems(
+            Update, 
+            animate_limb
+                .run_if(not_paused)
+                .after(recompute_limb)
+                .before(sync_ctrl_ball_transform)
+        )
+        .add_systems(
+            Update, 
+            apply_target_queue
+                .run_if(not_paused)
+                .after(move_limb)
+                .before(recompute_limb)
+        )
         .add_systems(
             Update, 
             recompute_limb 
+                .run_if(not_paused)
+                .run_if(not(fixed_solve))
+                .run_if(not(forward_kinematics))
                 .run_if(on_message::<GizmoUpdate>.or(on_message::<RecomputeLimb>))
                 .before(sync_ctrl_ball_transform)
         )
+        .add_systems(
+            Update, 
+            mark_pending_solve
+                .run_if(fixed_solve)
+                .run_if(on_message::<GizmoUpdate>.or(on_message::<RecomputeLimb>))
+                .after(apply_target_queue)
+        )
+        .add_systems(
+            FixedUpdate, 
+            (
+                capture_solved_pose,
+                (recompute_limb, clear_pending_solve)
+                    .chain()
+                    .run_if(not_paused)
+                    .run_if(not(forward_kinematics))
+                    .run_if(pending_solve),
+            )
+                .chain()
+                .run_if(fixed_solve)
+        )
+        .add_systems(
+            Update, 
+            interpolate_solved_pose
+                .run_if(fixed_solve)
+                .after(sync_ball_transform)
+                .after(sync_ctrl_ball_transform)
+                .after(sync_segment_transform)
+        )
+        .add_systems(
+            Update, 
+            apply_solve_rate.run_if(resource_changed::<UiState>)
+        )
+        .add_systems(
+            Update, 
+            templates::load_chain_template
+                .run_if(on_message::<LoadTemplate>)
+                .in_set(RespawnLimb)
+        )
+        .add_systems(
+            Update, 
+            session::load_session
+                .run_if(on_message::<LoadSession>)
+                .in_set(RespawnLimb)
+        )
+        .add_systems(
+            Update, 
+            edit_joint_count
+                .run_if(on_message::<EditJointCount>)
+                .in_set(RespawnLimb)
+        )
+        .add_systems(
+            Update, 
+            rebuild_segment_mesh.run_if(on_message::<RebuildSegmentMesh>)
+        )
         .add_systems(
             Update, 
             handle_limb_switch.run_if(resource_changed::<State<LimbState>>)
         )
+        .add_systems(Update, (draw_rest_deviation, draw_joint_frames, draw_reach_passes, draw_tool_offset))
+        .add_systems(Update, (highlight_self_intersections, highlight_unreachable_targets, draw_snap_grid, sync_target_markers))
         .add_systems(
             Update, 
             sync_ball_transform.run_if(on_message::<SyncTransform>)
@@ -103,16 +372,18 @@ fn main() {
         .add_systems(
             Update, 
             sync_segment_transform.run_if(on_message::<SyncTransform>)
-        )
-        .run();
+        );
+    
+    #[cfg(feature = "hot-reload")]
+    app.add_plugins(hot_reload::HotReloadPlugin);
+    #[cfg(feature = "telemetry")]
+    app.add_plugins(telemetry::TelemetryPlugin);
+    #[cfg(feature = "serde")]
+    app.add_systems(Update, config::load_config.run_if(on_message::<LoadConfig>).in_set(RespawnLimb));
+    
+    app.run();
 }
 
-// fn forwared_kinematics(kinematics_mode: Res<KinematicsMode>) {
-//     if *kinematics_mode != KinematicsMode::ForwardKinematics {
-//         return;
-//     }
-// }
-
 #[derive(Component, Default, Debug, Clone, Copy)]
 struct ControlBall {
     index: usize,
@@ -131,6 +402,14 @@ struct Segment {
 #[derive(Component, Default, Debug, Clone)]
 struct FantasyComponent;
 
+#[derive(Component, Default, Debug, Clone)]
+struct Floor;
+
+#[derive(Component, Default, Debug, Clone)]
+struct TargetMarker {
+    index: usize,
+}
+
 #[derive(Default, Message)]
 struct SyncTransform;
 
@@ -140,6 +419,19 @@ struct RecomputeLimb;
 #[derive(Default, Message)]
 struct MoveLimb;
 
+#[derive(Debug, Clone, Copy, Message)]
+struct RebuildSegmentMesh(usize);
+
+// Systems that despawn and respawn the limb entities; the sync systems must see the flushed entities.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+struct RespawnLimb;
+
+#[derive(Debug, Clone, Copy, Message)]
+enum EditJointCount {
+    Push,
+    Pop,
+}
+
 #[derive(States, Default, Debug, Hash, PartialEq, Eq, Clone, strum::EnumIter, strum::Display)]
 enum LimbState {
     #[default]
@@ -157,7 +449,6 @@ pub enum GizmoUpdate {
     },
     Drag {
         entity: Entity,
-        interaction: Option<TransformGizmoInteraction>,
     },
     Release {
         entity: Entity,
@@ -169,30 +460,49 @@ impl GizmoUpdate {
         match self {
             GizmoUpdate::Hover { entity } => &entity,
             GizmoUpdate::Grab { entity } => &entity,
-            GizmoUpdate::Drag {
-                entity,
-                interaction: _,
-            } => &entity,
+            GizmoUpdate::Drag { entity } => &entity,
             GizmoUpdate::Release { entity } => &entity,
         }
     }
 }
 
+fn default_joints() -> Vec<Vec3> {
+    vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(4.0, 0.0, 0.0),
+    ]
+}
+
+fn urdf_arg() -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--urdf");
+    args.next()?;
+    args.next()
+}
+
 fn setup(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut ev_sync_transforms: MessageWriter<SyncTransform>
 ) {
-    let joints = vec![
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(1.0, 0.0, 0.0),
-        Vec3::new(2.0, 0.0, 0.0),
-        Vec3::new(3.0, 0.0, 0.0),
-        Vec3::new(4.0, 0.0, 0.0),
-    ];
-    let mut limb = FabrikChain::new(joints, MotionHueristics::default());
-    commands.spawn(VelocityDisplay::default());
+    let mut limb = urdf_arg()
+        .and_then(|path| match FabrikChain::from_urdf(&path) {
+            Ok(limb) => Some(limb),
+            Err(err) => {
+                warn!("Could not load {path}: {err}");
+                None
+            }
+        })
+        .unwrap_or_else(|| FabrikChain::new(default_joints(), MotionHueristics::default()));
+    commands.spawn((
+        VelocityDisplay::default(),
+        SegmentVelocityDisplay::default(),
+        AccelerationDisplay::default(),
+        TransformHistory::default()
+    ));
     
     commands.spawn((
         PointLight {
@@ -204,78 +514,273 @@ fn setup(
         Transform::from_xyz(8.0, 16.0, 8.0),
     ));
     
-    let control_ball_mesh = meshes.add(
-        Mesh::from(Sphere::new(0.29).mesh().uv(32, 18))
-    );
-    let ball_mesh = meshes.add(
-        Mesh::from(Sphere::new(0.3).mesh().uv(32, 18))
-    );
-    let fantasy_ball_mesh = meshes.add(
-        Mesh::from(Sphere::new(0.3 * 0.999).mesh().uv(32, 18))
-    );
+    let limb_assets = LimbAssets {
+        control_ball_mesh: meshes.add(
+            Mesh::from(Sphere::new(0.29).mesh().uv(32, 18))
+        ),
+        ball_mesh: meshes.add(
+            Mesh::from(Sphere::new(0.3).mesh().uv(32, 18))
+        ),
+        fantasy_ball_mesh: meshes.add(
+            Mesh::from(Sphere::new(0.3 * 0.999).mesh().uv(32, 18))
+        ),
+        material: materials.add(StandardMaterial::default()),
+        fantasy_material: materials.add(StandardMaterial {
+            base_color: Color::linear_rgba(0.19, 0.0, 0.5, 1.0),
+            ..default()
+        }),
+        transculent_material: materials.add(StandardMaterial {
+            alpha_mode: AlphaMode::Mask(0.5),
+            base_color: Color::linear_rgba(0.7, 0.7, 1.0, 0.2),
+            ..default()
+        }),
+        collision_material: materials.add(StandardMaterial {
+            base_color: Color::linear_rgba(0.9, 0.05, 0.05, 1.0),
+            ..default()
+        }),
+        target_mesh: meshes.add(
+            Mesh::from(Sphere::new(0.12).mesh().uv(16, 9))
+        ),
+        target_material: materials.add(StandardMaterial {
+            base_color: Color::linear_rgba(0.1, 0.9, 0.3, 1.0),
+            unlit: true,
+            ..default()
+        }),
+    };
     
-    let material = materials.add(StandardMaterial::default());
-    let fantasy_material = materials.add(StandardMaterial {
-        base_color: Color::linear_rgba(0.19, 0.0, 0.5, 1.0),
-        ..default()
-    });
-    let transculent_material = materials.add(StandardMaterial {
-        alpha_mode: AlphaMode::Mask(0.5),
-        base_color: Color::linear_rgba(0.7, 0.7, 1.0, 0.2),
-        ..default()
-    });
+    spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &limb);
+    commands.insert_resource(limb_assets);
+    limb.finalize();
+    commands.spawn(LimbData(limb));
+    
+    ev_sync_transforms.write_default();
+    
+    commands.spawn((
+        Mesh3d(meshes.add(Plane3d::default().mesh().size(20.0, 20.0))),
+        MeshMaterial3d(materials.add(StandardMaterial {
+            base_color: Color::linear_rgba(0.05, 0.05, 0.06, 1.0),
+            ..default()
+        })),
+        Transform::from_xyz(0.0, FLOOR_HEIGHT, 0.0),
+        Floor
+    ));
     
+    let orbit = OrbitCamera::default();
+    commands.spawn((
+        Camera3d::default(),
+        orbit.transform(),
+        orbit,
+    ));
+}
+
+fn spawn_limb_entities(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    limb_assets: &LimbAssets,
+    limb: &FabrikChain
+) {
     for i in 0..limb.joints.len() {
         let transform = Transform::from_translation(limb.joints[i]);
         commands.spawn((
-            Mesh3d(ball_mesh.clone()),
-            MeshMaterial3d(material.clone()),
+            Mesh3d(limb_assets.ball_mesh.clone()),
+            MeshMaterial3d(limb_assets.material.clone()),
             transform,
-            InnerBall { index: i }
+            InnerBall { index: i },
+            Pickable::IGNORE
         ));
         
         commands.spawn((
-            Mesh3d(control_ball_mesh.clone()),
-            MeshMaterial3d(transculent_material.clone()),
+            Mesh3d(limb_assets.control_ball_mesh.clone()),
+            MeshMaterial3d(limb_assets.transculent_material.clone()),
             Transform::from_translation(limb.joints[i]),
             ControlBall { index: i }
         ));
         
         commands.spawn((
-            Mesh3d(fantasy_ball_mesh.clone()),
-            MeshMaterial3d(fantasy_material.clone()),
+            Mesh3d(limb_assets.fantasy_ball_mesh.clone()),
+            MeshMaterial3d(limb_assets.fantasy_material.clone()),
             transform,
             InnerBall { index: i },
-            FantasyComponent
+            FantasyComponent,
+            Pickable::IGNORE
         ));
     }
     
     for i in 0..limb.lengths.len() {
-        let mesh = meshes.add(Mesh::from(Cylinder::new(0.15, limb.lengths[i])));
-        let fantasy_mesh = meshes.add(Mesh::from(Cylinder::new(0.15 * 0.999, limb.lengths[i])));
+        let mesh = meshes.add(Mesh::from(Cylinder::new(SEGMENT_RADIUS, limb.lengths[i])));
+        let fantasy_mesh = meshes.add(Mesh::from(Cylinder::new(SEGMENT_RADIUS * 0.999, limb.lengths[i])));
         commands.spawn((
             Mesh3d(fantasy_mesh.clone()),
-            MeshMaterial3d(fantasy_material.clone()),
+            MeshMaterial3d(limb_assets.fantasy_material.clone()),
             Transform::from(limb.segment_transforms[i]),
             Segment { index: i },
             FantasyComponent
         ));
         commands.spawn((
             Mesh3d(mesh.clone()),
-            MeshMaterial3d(material.clone()),
+            MeshMaterial3d(limb_assets.material.clone()),
             Transform::from(limb.segment_transforms[i]),
             Segment { index: i }
         ));
     }
-    limb.finalize();
-    commands.spawn(LimbData(limb));
+}
+
+fn despawn_limb_entities(
+    commands: &mut Commands,
+    query_limb_entities: &Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>
+) {
+    for entity in query_limb_entities.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn subdivide_segment(
+    click: On<Pointer<Click>>,
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chain: Query<&mut LimbData>,
+    query_segment: Query<&Segment, Without<FantasyComponent>>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    ui_state: Res<UiState>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    if !ui_state.subdivide {
+        return;
+    }
+    let (Ok(segment), Some(hit)) = (query_segment.get(click.event_target()), click.hit.position) else {
+        return;
+    };
+    
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    let pos = chain.0.closest_point(segment.index, hit);
+    chain.0.insert_joint(segment.index, pos);
+    
+    despawn_limb_entities(&mut commands, &query_limb_entities);
+    spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &chain.0);
+    ev_sync_transforms.write_default();
+}
+
+fn edit_joint_count(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chain: Query<&mut LimbData>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    mut ev_edit_joint_count: MessageReader<EditJointCount>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    
+    for edit in ev_edit_joint_count.read() {
+        match edit {
+            EditJointCount::Push => chain.0.push_joint(DEFAULT_SEGMENT_LENGTH),
+            EditJointCount::Pop => chain.0.pop_joint(),
+        }
+    }
     
+    despawn_limb_entities(&mut commands, &query_limb_entities);
+    spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &chain.0);
     ev_sync_transforms.write_default();
+}
+
+fn rebuild_segment_mesh(
+    mut meshes: ResMut<Assets<Mesh>>,
+    query_chain: Query<&LimbData>,
+    query_segment: Query<(&Segment, &Mesh3d, Has<FantasyComponent>)>,
+    mut ev_rebuild_segment_mesh: MessageReader<RebuildSegmentMesh>
+) {
+    let Ok(chain) = query_chain.single() else { return; };
     
-    commands.spawn((
-        Camera3d::default(),
-        Transform::from_xyz(0.0, 6.0, 7.0).looking_at(Vec3::ZERO, Vec3::Y),
-    ));
+    for RebuildSegmentMesh(index) in ev_rebuild_segment_mesh.read() {
+        for (segment, mesh, fantasy) in query_segment.iter() {
+            if segment.index != *index {
+                continue;
+            }
+            let (limb, radius) = if fantasy {
+                (chain.get(&LimbState::FantasyLimb), SEGMENT_RADIUS * 0.999)
+            } else {
+                (chain.get(&LimbState::RealLimb), SEGMENT_RADIUS)
+            };
+            let (Some(length), Some(mesh)) = (limb.lengths.get(*index), meshes.get_mut(&mesh.0)) else { continue; };
+            *mesh = Mesh::from(Cylinder::new(radius, *length));
+        }
+    }
+}
+
+fn grab_control_ball(
+    drag_start: On<Pointer<DragStart>>,
+    query_ctrl_ball: Query<(), With<ControlBall>>,
+    mut ev_gizmo: MessageWriter<GizmoUpdate>
+) {
+    let entity = drag_start.event_target();
+    if drag_start.button != PointerButton::Primary || query_ctrl_ball.get(entity).is_err() {
+        return;
+    }
+    ev_gizmo.write(GizmoUpdate::Grab { entity });
+}
+
+fn drag_control_ball(
+    drag: On<Pointer<Drag>>,
+    mut query_ctrl_ball: Query<&mut Transform, With<ControlBall>>,
+    query_camera: Query<(&Camera, &GlobalTransform)>,
+    mut ev_gizmo: MessageWriter<GizmoUpdate>
+) {
+    if drag.button != PointerButton::Primary {
+        return;
+    }
+    let entity = drag.event_target();
+    let Ok(mut transform) = query_ctrl_ball.get_mut(entity) else { return; };
+    let Ok((camera, camera_transform)) = query_camera.single() else { return; };
+    let Ok(ray) = camera.viewport_to_world(camera_transform, drag.pointer_location.position) else { return; };
+    
+    let plane = InfinitePlane3d::new(camera_transform.forward());
+    let Some(distance) = ray.intersect_plane(transform.translation, plane) else { return; };
+    transform.translation = ray.get_point(distance);
+    ev_gizmo.write(GizmoUpdate::Drag { entity });
+}
+
+fn release_control_ball(
+    drag_end: On<Pointer<DragEnd>>,
+    query_ctrl_ball: Query<(), With<ControlBall>>,
+    mut ev_gizmo: MessageWriter<GizmoUpdate>
+) {
+    let entity = drag_end.event_target();
+    if drag_end.button != PointerButton::Primary || query_ctrl_ball.get(entity).is_err() {
+        return;
+    }
+    ev_gizmo.write(GizmoUpdate::Release { entity });
+}
+
+fn nearest_joint(joints: &[Vec3], point: Vec3) -> Option<usize> {
+    joints
+        .iter()
+        .enumerate()
+        .min_by(|(_, a), (_, b)| a.distance_squared(point).total_cmp(&b.distance_squared(point)))
+        .map(|(i, _)| i)
+}
+
+fn anchor_to_surface(
+    click: On<Pointer<Click>>,
+    query_chain: Query<&LimbData>,
+    query_floor: Query<(), With<Floor>>,
+    mut target_queue: ResMut<TargetQueue>,
+    mut ev_recompute: MessageWriter<RecomputeLimb>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>
+) {
+    if !ui_state.anchor_on_click {
+        return;
+    }
+    let (Ok(()), Some(hit)) = (query_floor.get(click.event_target()), click.hit.position) else {
+        return;
+    };
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let Some(index) = nearest_joint(&chain.get(limb_state.get()).joints, hit) else { return; };
+    target_queue.0.push(TargetCommand::Anchor(index, hit));
+    target_queue.0.push(TargetCommand::Set(index, hit));
+    ev_recompute.write_default();
 }
 
 fn sync_ball_transform(
@@ -283,14 +788,113 @@ fn sync_ball_transform(
     mut query_ball: Query<(&InnerBall, &mut Transform), Without<FantasyComponent>>,
     mut query_ball_fantasy: Query<(&InnerBall, &mut Transform), With<FantasyComponent>>
 ) {
-    let chain = query_chain.single_mut().unwrap();
+    let Ok(chain) = query_chain.single_mut() else { return; };
     
     for (ball, mut transform) in query_ball.iter_mut() {
-        *transform = Transform::from_translation(chain.0.joints[ball.index]);
+        let Some(joint) = chain.0.joints.get(ball.index) else { continue; };
+        *transform = Transform::from_translation(*joint);
     }
     
+    let fantasy = chain.get(&LimbState::FantasyLimb);
     for (ball, mut transform) in query_ball_fantasy.iter_mut() {
-        *transform = Transform::from_translation(chain.0.limb.as_ref().unwrap().joints[ball.index]);
+        let Some(joint) = fantasy.joints.get(ball.index) else { continue; };
+        *transform = Transform::from_translation(*joint);
+    }
+}
+
+fn not_paused(paused: Res<Paused>) -> bool {
+    !paused.0
+}
+
+fn fixed_solve(ui_state: Res<UiState>) -> bool {
+    ui_state.fixed_solve
+}
+
+fn forward_kinematics(ui_state: Res<UiState>) -> bool {
+    ui_state.forward_kinematics
+}
+
+fn pending_solve(pending_solve: Res<PendingSolve>) -> bool {
+    pending_solve.0
+}
+
+fn mark_pending_solve(mut pending_solve: ResMut<PendingSolve>) {
+    pending_solve.0 = true;
+}
+
+fn clear_pending_solve(mut pending_solve: ResMut<PendingSolve>) {
+    pending_solve.0 = false;
+}
+
+fn solve_timestep(solve_hz: f64) -> Duration {
+    Duration::from_secs_f64(1.0 / solve_hz.max(1.0))
+}
+
+fn apply_solve_rate(ui_state: Res<UiState>, mut time: ResMut<Time<Fixed>>) {
+    let timestep = solve_timestep(ui_state.solve_hz);
+    if time.timestep() != timestep {
+        time.set_timestep(timestep);
+    }
+}
+
+fn capture_solved_pose(
+    query_chain: Query<&LimbData>,
+    mut previous: ResMut<PreviousSolvedPose>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    previous.joints.clone_from(&limb.joints);
+    previous.segments.clone_from(&limb.segment_transforms);
+}
+
+fn interpolate_solved_pose(
+    time: Res<Time<Fixed>>,
+    query_chain: Query<&LimbData>,
+    previous: Res<PreviousSolvedPose>,
+    mut query_ball: Query<(&InnerBall, &mut Transform, Has<FantasyComponent>), Without<Segment>>,
+    mut query_segment: Query<(&Segment, &mut Transform, Has<FantasyComponent>), Without<InnerBall>>,
+    mut query_ctrl_ball: Query<(&ControlBall, &mut Transform), (Without<InnerBall>, Without<Segment>)>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    let fantasy = *limb_state.get() == LimbState::FantasyLimb;
+    let alpha = time.overstep_fraction();
+    
+    for (ball, mut transform, is_fantasy) in query_ball.iter_mut() {
+        let (Some(from), Some(to)) = (previous.joints.get(ball.index), limb.joints.get(ball.index)) else { continue; };
+        if is_fantasy == fantasy {
+            transform.translation = from.lerp(*to, alpha);
+        }
+    }
+    for (ctrl_ball, mut transform) in query_ctrl_ball.iter_mut() {
+        let (Some(from), Some(to)) = (previous.joints.get(ctrl_ball.index), limb.joints.get(ctrl_ball.index)) else { continue; };
+        transform.translation = from.lerp(*to, alpha);
+    }
+    for (segment, mut transform, is_fantasy) in query_segment.iter_mut() {
+        let (Some(from), Some(to)) = (previous.segments.get(segment.index), limb.segment_transforms.get(segment.index)) else { continue; };
+        if is_fantasy == fantasy {
+            *transform = Transform {
+                translation: from.translation.lerp(to.translation, alpha),
+                rotation: from.rotation.slerp(to.rotation, alpha),
+                scale: from.scale.lerp(to.scale, alpha),
+            };
+        }
+    }
+}
+
+fn roll_solve_budget(mut solve_budget: ResMut<SolveBudget>) {
+    let frame_ms = std::mem::take(&mut solve_budget.current).as_secs_f32() * 1e3;
+    solve_budget.history.push_back(frame_ms);
+    if solve_budget.history.len() > SOLVE_BUDGET_WINDOW {
+        solve_budget.history.pop_front();
+    }
+}
+
+fn toggle_pause(keys: Res<ButtonInput<KeyCode>>, mut paused: ResMut<Paused>) {
+    if keys.just_pressed(KeyCode::Space) {
+        paused.0 = !paused.0;
     }
 }
 
@@ -303,11 +907,12 @@ fn sync_ctrl_ball_transform(
     mut query_ctrl_ball: Query<(&ControlBall, &mut Transform)>,
     limb_state: Res<State<LimbState>>,
 ) {
-    let chain = query_chain.single_mut().unwrap();
+    let Ok(chain) = query_chain.single_mut() else { return; };
     let limb = chain.get(&limb_state.get());
     
     for (ctrl_ball, mut transform) in query_ctrl_ball.iter_mut() {
-        *transform = Transform::from_translation(limb.joints[ctrl_ball.index]);
+        let Some(joint) = limb.joints.get(ctrl_ball.index) else { continue; };
+        *transform = Transform::from_translation(*joint);
     }
 }
 
@@ -316,52 +921,195 @@ fn sync_segment_transform(
     mut query_segment: Query<(&Segment, &mut Transform), Without<FantasyComponent>>,
     mut query_segment_fantasy: Query<(&Segment, &mut Transform), With<FantasyComponent>>
 ) {
-    let chain = query_chain.single_mut().unwrap();
+    let Ok(chain) = query_chain.single_mut() else { return; };
     for (segment, mut transform) in query_segment.iter_mut() {
-        *transform = chain.0.segment_transforms[segment.index];
+        let Some(segment_transform) = chain.0.segment_transforms.get(segment.index) else { continue; };
+        *transform = *segment_transform;
     }
+    let fantasy = chain.get(&LimbState::FantasyLimb);
     for (segment, mut transform) in query_segment_fantasy.iter_mut() {
-        *transform = chain.0.limb.as_ref().unwrap().segment_transforms[segment.index];
+        let Some(segment_transform) = fantasy.segment_transforms.get(segment.index) else { continue; };
+        *transform = *segment_transform;
     }
 }
 
-fn move_limb(
-    query_ctrl_ball: Query<(&ControlBall, &Transform)>,
-    mut query_chain: Query<&mut LimbData>,
-    mut ev_gizmo: MessageReader<GizmoUpdate>,
-    mut ev_recompute: MessageWriter<RecomputeLimb>,
-    limb_state: Res<State<LimbState>>
+fn highlight_self_intersections(
+    query_chain: Query<&LimbData>,
+    mut query_segment: Query<(&Segment, &mut MeshMaterial3d<StandardMaterial>), Without<FantasyComponent>>,
+    limb_assets: Res<LimbAssets>
 ) {
-    let mut excluded = Vec::new();
-    if ev_gizmo.is_empty() { return; }
-    
-    let mut chain = query_chain.single_mut().unwrap();
-    let limb = chain.get_mut(limb_state.get());
-    limb.targets.clear();
+    let Ok(chain) = query_chain.single() else { return; };
+    let colliding: Vec<usize> = chain.0
+        .self_intersections(SEGMENT_RADIUS)
+        .into_iter()
+        .flat_map(|(a, b)| [a, b])
+        .collect();
     
-    for event in ev_gizmo.read() {
-        let entity = event.entity().clone();
-        let (ball, transform) = query_ctrl_ball
+    for (segment, mut material) in query_segment.iter_mut() {
+        let wanted = if colliding.contains(&segment.index) {
+            &limb_assets.collision_material
+        } else {
+            &limb_assets.material
+        };
+        if material.0 != *wanted {
+            material.0 = wanted.clone();
+        }
+    }
+}
+
+fn highlight_unreachable_targets(
+    query_chain: Query<&LimbData>,
+    mut query_ctrl_ball: Query<(&ControlBall, &mut MeshMaterial3d<StandardMaterial>)>,
+    last_solve: Res<LastSolve>,
+    limb_assets: Res<LimbAssets>
+) {
+    let Ok(chain) = query_chain.single() else { return; };
+    let unreachable = !last_solve.reachable();
+    
+    for (ball, mut material) in query_ctrl_ball.iter_mut() {
+        let wanted = if unreachable && chain.0.targets.iter().any(|(index, _)| *index == ball.index) {
+            &limb_assets.collision_material
+        } else {
+            &limb_assets.transculent_material
+        };
+        if material.0 != *wanted {
+            material.0 = wanted.clone();
+        }
+    }
+}
+
+fn sync_target_markers(
+    mut commands: Commands,
+    query_chain: Query<&LimbData>,
+    mut query_markers: Query<(Entity, &TargetMarker, &mut Transform)>,
+    limb_assets: Res<LimbAssets>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Ok(chain) = query_chain.single() else { return; };
+    let targets = &chain.get(limb_state.get()).targets;
+    
+    let existing = query_markers.iter().count();
+    for (entity, marker, mut transform) in query_markers.iter_mut() {
+        match targets.get(marker.index) {
+            Some((_, pos)) if transform.translation != *pos => transform.translation = *pos,
+            Some(_) => {}
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for (index, (_, pos)) in targets.iter().enumerate().skip(existing) {
+        commands.spawn((
+            Mesh3d(limb_assets.target_mesh.clone()),
+            MeshMaterial3d(limb_assets.target_material.clone()),
+            Transform::from_translation(*pos),
+            Pickable::IGNORE,
+            TargetMarker { index }
+        ));
+    }
+}
+
+fn move_limb(
+    query_ctrl_ball: Query<(&ControlBall, &Transform)>,
+    query_chain: Query<&LimbData>,
+    mut ev_gizmo: MessageReader<GizmoUpdate>,
+    mut ev_move_limb: MessageReader<MoveLimb>,
+    mut ev_recompute: MessageWriter<RecomputeLimb>,
+    mut desired_targets: ResMut<DesiredTargets>,
+    mut target_queue: ResMut<TargetQueue>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>
+) {
+    let mut excluded = Vec::new();
+    if !ev_move_limb.is_empty() {
+        // targets were queued by the sender
+        ev_move_limb.clear();
+        ev_recompute.write_default();
+    }
+    if ev_gizmo.is_empty() { return; }
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    target_queue.0.push(TargetCommand::ClearAll);
+    desired_targets.0.clear();
+    
+    for event in ev_gizmo.read() {
+        let entity = event.entity().clone();
+        let (ball, transform) = query_ctrl_ball
             .get(entity)
             .expect("Something is moving but it's not a ball!");
         excluded.push(ball.index);
-        limb.targets
-            .push((ball.index, transform.translation.clone()));
+        let pos = match ui_state.snap_grid {
+            Some(spacing) => snap_to_grid(transform.translation, spacing),
+            None => transform.translation,
+        };
+        desired_targets.0
+            .push((ball.index, pos));
+        target_queue.0
+            .push(TargetCommand::Set(ball.index, ease_toward(limb.joints[ball.index], pos, ui_state.drag_smoothing)));
     }
     
     ev_recompute.write_default();
 }
 
-fn recompute_limb(
+fn apply_target_queue(
     mut query_chain: Query<&mut LimbData>,
-    mut query_velocity_display: Query<&mut VelocityDisplay>,
-    mut ev_sync_transform: MessageWriter<SyncTransform>,
+    mut target_queue: ResMut<TargetQueue>,
     limb_state: Res<State<LimbState>>
-) {    let mut chain = query_chain.single_mut().unwrap();
-    let limb = chain.get_mut(limb_state.get());
+) {
+    if target_queue.0.is_empty() { return; }
     
-    limb.solve(10, PoseDiscrepancy::default(), &mut KinematicsMode::InverseKinematics);
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    chain
+        .get_mut(limb_state.get())
+        .apply_target_commands(target_queue.0.drain(..));
+}
+
+fn snap_to_grid(pos: Vec3, spacing: f32) -> Vec3 {
+    (pos / spacing).round() * spacing
+}
+
+fn ease_toward(current: Vec3, desired: Vec3, smoothing: f32) -> Vec3 {
+    current.lerp(desired, 1.0 - smoothing)
+}
+
+fn ease_targets(
+    query_chain: Query<&LimbData>,
+    mut desired_targets: ResMut<DesiredTargets>,
+    mut target_queue: ResMut<TargetQueue>,
+    mut ev_recompute: MessageWriter<RecomputeLimb>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>
+) {
+    if desired_targets.0.is_empty() { return; }
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    
+    let mut settled = true;
+    for (index, desired) in desired_targets.0.iter() {
+        if let Some((_, target)) = limb.targets.iter().find(|(i, _)| i == index) {
+            let mut eased = ease_toward(*target, *desired, ui_state.drag_smoothing);
+            if eased.distance(*desired) < DRAG_SETTLE_DISTANCE {
+                eased = *desired;
+            } else {
+                settled = false;
+            }
+            target_queue.0.push(TargetCommand::Set(*index, eased));
+        }
+    }
+    if settled {
+        desired_targets.0.clear();
+    }
     
+    ev_recompute.write_default();
+}
+
+fn record_motion(
+    limb: &FabrikChain,
+    query_velocity_display: &mut Query<&mut VelocityDisplay>,
+    query_segment_velocity_display: &mut Query<&mut SegmentVelocityDisplay>,
+    query_acceleration_display: &mut Query<&mut AccelerationDisplay>,
+    query_transform_history: &mut Query<&mut TransformHistory>
+) {
     if !limb.angular_velocities.is_empty() {
         query_velocity_display
             .single_mut()
@@ -369,16 +1117,289 @@ fn recompute_limb(
             .0
             .push(limb.angular_velocities.clone());
     }
+    if !limb.segment_velocities.is_empty() {
+        query_segment_velocity_display
+            .single_mut()
+            .unwrap()
+            .0
+            .push(limb.segment_velocities.clone());
+    }
+    if !limb.angular_accelerations.is_empty() {
+        query_acceleration_display
+            .single_mut()
+            .unwrap()
+            .0
+            .push(limb.angular_accelerations.clone());
+    }
+    let mut transform_history = query_transform_history.single_mut().unwrap();
+    transform_history.0.push_back(limb.segment_transforms.clone());
+    if transform_history.0.len() > SESSION_HISTORY_CAP {
+        transform_history.0.pop_front();
+    }
+}
+
+fn recompute_limb(
+    mut query_chain: Query<&mut LimbData>,
+    mut query_velocity_display: Query<&mut VelocityDisplay>,
+    mut query_segment_velocity_display: Query<&mut SegmentVelocityDisplay>,
+    mut query_acceleration_display: Query<&mut AccelerationDisplay>,
+    mut query_transform_history: Query<&mut TransformHistory>,
+    mut ev_sync_transform: MessageWriter<SyncTransform>,
+    mut last_solve: ResMut<LastSolve>,
+    mut solve_budget: ResMut<SolveBudget>,
+    mut animation_goal: ResMut<AnimationGoal>,
+    mut ui_state: ResMut<UiState>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    let limb = chain.get_mut(limb_state.get());
+    
+    let pose_discrepancy = ui_state.pose_discrepancy;
+    if ui_state.animate {
+        let mut solved = limb.clone();
+        let start = Instant::now();
+        last_solve.0 = Some(solved.solve_blended(10, pose_discrepancy, &mut ui_state.kinematics_mode));
+        solve_budget.current += start.elapsed();
+        limb.prev_targets = solved.prev_targets;
+        limb.warm_start_cache = solved.warm_start_cache;
+        animation_goal.0 = Some(solved.joints);
+        return;
+    }
+    
+    let start = Instant::now();
+    last_solve.0 = Some(limb.solve_blended(10, pose_discrepancy, &mut ui_state.kinematics_mode));
+    solve_budget.current += start.elapsed();
+    animation_goal.0 = None;
     
+    record_motion(
+        limb,
+        &mut query_velocity_display,
+        &mut query_segment_velocity_display,
+        &mut query_acceleration_display,
+        &mut query_transform_history
+    );
     ev_sync_transform.write_default();
 }
 
+fn animate_limb(
+    time: Res<Time>,
+    mut query_chain: Query<&mut LimbData>,
+    mut query_velocity_display: Query<&mut VelocityDisplay>,
+    mut query_segment_velocity_display: Query<&mut SegmentVelocityDisplay>,
+    mut query_acceleration_display: Query<&mut AccelerationDisplay>,
+    mut query_transform_history: Query<&mut TransformHistory>,
+    mut animation_goal: ResMut<AnimationGoal>,
+    mut ev_sync_transform: MessageWriter<SyncTransform>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Some(goal) = animation_goal.0.as_ref() else { return; };
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    let limb = chain.get_mut(limb_state.get());
+    if goal.len() != limb.joints.len() {
+        animation_goal.0 = None;
+        return;
+    }
+    
+    let t = 1.0 - (-ui_state.animation_speed * time.delta_secs()).exp();
+    let mut remaining: f32 = 0.0;
+    for (joint, target) in limb.joints.iter_mut().zip(goal.iter()) {
+        *joint = joint.lerp(*target, t);
+        remaining = remaining.max(joint.distance(*target));
+    }
+    if remaining <= DRAG_SETTLE_DISTANCE {
+        limb.joints = goal.clone();
+        animation_goal.0 = None;
+    }
+    limb.recalculate_angles();
+    limb.recalculate_segments();
+    
+    record_motion(
+        limb,
+        &mut query_velocity_display,
+        &mut query_segment_velocity_display,
+        &mut query_acceleration_display,
+        &mut query_transform_history
+    );
+    ev_sync_transform.write_default();
+}
+
+fn deviation_rgb(deviation: f32) -> [u8; 3] {
+    match deviation.to_degrees() {
+        d if d < 10.0 => [60, 200, 80],
+        d if d < 30.0 => [230, 200, 40],
+        _ => [220, 50, 50],
+    }
+}
+
+fn draw_rest_deviation(
+    mut gizmos: Gizmos,
+    query_chain: Query<&LimbData>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>,
+) {
+    if !ui_state.show_rest_deviation {
+        return;
+    }
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    
+    for (i, deviation) in limb.rest_deviations().into_iter().enumerate() {
+        let [r, g, b] = deviation_rgb(deviation);
+        gizmos.sphere(Isometry3d::from_translation(limb.joints[i + 1]), 0.35, Color::srgb_u8(r, g, b));
+    }
+}
+
+fn draw_joint_frames(
+    mut gizmos: Gizmos,
+    query_chain: Query<&LimbData>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>,
+) {
+    if !ui_state.show_frames {
+        return;
+    }
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    
+    for (i, joint) in limb.joints.iter().enumerate() {
+        let segment = limb.segment_transforms[i.min(limb.segment_transforms.len() - 1)];
+        gizmos.axes(Transform::from_translation(*joint).with_rotation(segment.rotation), 0.6);
+    }
+}
+
+fn downsample_min_max(points: Vec<[f64; 2]>, buckets: usize) -> Vec<[f64; 2]> {
+    if buckets == 0 || points.len() <= buckets * 2 {
+        return points;
+    }
+    
+    let bucket_len = points.len().div_ceil(buckets);
+    let mut downsampled = Vec::with_capacity(buckets * 2);
+    for bucket in points.chunks(bucket_len) {
+        let by_value = |a: &&[f64; 2], b: &&[f64; 2]| a[1].total_cmp(&b[1]);
+        let min = bucket.iter().min_by(by_value).unwrap();
+        let max = bucket.iter().max_by(by_value).unwrap();
+        if min[0] <= max[0] {
+            downsampled.extend([*min, *max]);
+        } else {
+            downsampled.extend([*max, *min]);
+        }
+    }
+    downsampled.dedup();
+    downsampled
+}
+
+fn draw_tool_offset(
+    mut gizmos: Gizmos,
+    query_chain: Query<&LimbData>,
+    limb_state: Res<State<LimbState>>,
+) {
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    if limb.tool_offset == Vec3::ZERO {
+        return;
+    }
+    
+    let (last, ee) = (*limb.joints.last().unwrap(), limb.get_ee());
+    gizmos.line(last, ee, Color::srgb(1.0, 0.8, 0.2));
+    gizmos.sphere(Isometry3d::from_translation(ee), 0.08, Color::srgb(1.0, 0.8, 0.2));
+}
+
+fn history_plot(ui: &mut Ui, id: &str, history: &[Vec<f32>], downsample: bool) {
+    let mut velocities = Vec::new();
+    if let Some(first_len) = history.first().map(|x| x.len()) {
+        for _ in 0..first_len {
+            velocities.push(Vec::new());
+        }
+        for x in 0..history.len() {
+            for y in 0..history[x].len() {
+                let new_point = [x as f64, history[x][y] as f64];
+                match velocities.get_mut(y) {
+                    Some(y_ptr) => {
+                        y_ptr.push(new_point);
+                    }
+                    None => {
+                        velocities.push(vec![new_point]);
+                    }
+                }
+            }
+        }
+        
+        let lines = velocities
+            .into_iter()
+            .map(|x| if downsample { downsample_min_max(x, PLOT_BUCKETS) } else { x })
+            .map(|x| Line::new("Plot #1", PlotPoints::new(x)));
+        
+        Plot::new(id)
+            .view_aspect(2.0)
+            .show(ui, |plot_ui| {
+                for line in lines {
+                    plot_ui.line(line);
+                }
+            });
+    } else {
+        ui.label("NO DATA");
+    }
+}
+
+fn draw_reach_passes(
+    mut gizmos: Gizmos,
+    query_chain: Query<&LimbData>,
+    ui_state: Res<UiState>,
+    limb_state: Res<State<LimbState>>,
+) {
+    if !ui_state.show_passes {
+        return;
+    }
+    
+    let Ok(chain) = query_chain.single() else { return; };
+    let limb = chain.get(limb_state.get());
+    
+    for (joint, pass) in limb.joints.iter().zip(limb.pass_touched.iter()) {
+        let color = match pass {
+            ReachPass::Untouched => continue,
+            ReachPass::Forward => Color::srgb(1.0, 0.5, 0.0),
+            ReachPass::Backward => Color::srgb(0.0, 0.8, 1.0),
+        };
+        gizmos.sphere(Isometry3d::from_translation(*joint), 0.35, color);
+    }
+}
+
+fn format_joint(index: usize, label: Option<&str>, pos: Vec3) -> String {
+    match label {
+        Some(label) => format!("{index} ({label}): {:.3}, {:.3}, {:.3}", pos.x, pos.y, pos.z),
+        None => format!("{index}: {:.3}, {:.3}, {:.3}", pos.x, pos.y, pos.z),
+    }
+}
+
+fn draw_snap_grid(mut gizmos: Gizmos, ui_state: Res<UiState>) {
+    let Some(spacing) = ui_state.snap_grid else { return; };
+    
+    let cells = (10.0 / spacing).ceil() as u32;
+    gizmos.grid(
+        Isometry3d::from_rotation(Quat::from_rotation_x(std::f32::consts::FRAC_PI_2)),
+        UVec2::splat(cells),
+        Vec2::splat(spacing),
+        Color::srgba(1.0, 1.0, 1.0, 0.08)
+    );
+}
+
 fn display_ui(
     mut context: EguiContexts,
     mut query: Query<&mut VelocityDisplay>,
+    mut query_segment_velocity: Query<&mut SegmentVelocityDisplay>,
+    mut query_acceleration: Query<&mut AccelerationDisplay>,
+    mut query_transform_history: Query<&mut TransformHistory>,
     mut query_chain: Query<&mut LimbData>,
     mut ui_state: ResMut<UiState>,
-    mut ev_sync_transforms: MessageWriter<SyncTransform>,
+    mut paused: ResMut<Paused>,
+    (last_solve, solve_budget): (Res<LastSolve>, Res<SolveBudget>),
+    (mut pose_library, mut pose_recall): (ResMut<PoseLibrary>, ResMut<PoseRecall>),
+    (mut target_queue, mut query_ctrl_ball, mut ev_move_limb): (ResMut<TargetQueue>, Query<(&ControlBall, &mut Transform)>, MessageWriter<MoveLimb>),
+    (mut ev_sync_transforms, mut ev_recompute, mut ev_rebuild_segment_mesh, mut ev_reset_camera): (MessageWriter<SyncTransform>, MessageWriter<RecomputeLimb>, MessageWriter<RebuildSegmentMesh>, MessageWriter<ResetCamera>),
+    (chain_templates, mut ev_load_template, mut ev_load_session, mut ev_load_config, mut ev_edit_joint_count): (Res<ChainTemplates>, MessageWriter<LoadTemplate>, MessageWriter<LoadSession>, MessageWriter<LoadConfig>, MessageWriter<EditJointCount>),
     limb_state_ro: ResMut<State<LimbState>>,
     mut limb_state: ResMut<NextState<LimbState>>,
     mut frame_count: Local<u32> 
@@ -388,33 +1409,284 @@ fn display_ui(
         return;
     }
     
-    let mut chain = query_chain.single_mut().unwrap();
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
     
     Window::new("Limb Control").show(context.ctx_mut().unwrap(), |ui| {
         let mut velocity_display = query.single_mut().unwrap();
+        let mut segment_velocity_display = query_segment_velocity.single_mut().unwrap();
+        let mut acceleration_display = query_acceleration.single_mut().unwrap();
+        let mut transform_history = query_transform_history.single_mut().unwrap();
         
+        if ui
+            .button(if paused.0 { "Resume" } else { "Pause" })
+            .clicked()
+        {
+            paused.0 = !paused.0;
+        }
         if ui
             .button("Reset Graph")
             .clicked() 
         {
             velocity_display.0.clear();
+            segment_velocity_display.0.clear();
+            acceleration_display.0.clear();
         }
         if ui
             .button("Reset All")
             .clicked() 
         {
             velocity_display.0.clear();
+            segment_velocity_display.0.clear();
+            acceleration_display.0.clear();
+            transform_history.0.clear();
             chain.0.reset();
             ev_sync_transforms.write_default();
         }
+        if ui
+            .button("Save Session")
+            .clicked()
+        {
+            let session = Session::capture(
+                &chain.0.joints,
+                &velocity_display.0,
+                &acceleration_display.0,
+                &segment_velocity_display.0
+            );
+            if let Err(err) = session.save(SESSION_FILE) {
+                error!("Could not save {SESSION_FILE}: {err}");
+            }
+        }
+        if ui
+            .button("Load Session")
+            .clicked()
+        {
+            ev_load_session.write_default();
+        }
+        #[cfg(feature = "serde")]
+        if ui
+            .button("Save Config")
+            .clicked()
+        {
+            ui_state.config_error = chain.0
+                .save_json(config::CONFIG_FILE)
+                .err()
+                .map(|err| format!("Could not save {}: {err}", config::CONFIG_FILE));
+        }
+        #[cfg(feature = "serde")]
+        if ui
+            .button("Load Config")
+            .clicked()
+        {
+            ev_load_config.write_default();
+        }
+        if let Some(err) = ui_state.config_error.as_ref() {
+            ui.colored_label(Color32::RED, err);
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .button("+Joint")
+                .clicked()
+            {
+                ev_edit_joint_count.write(EditJointCount::Push);
+            }
+            if ui
+                .add_enabled(chain.0.joints.len() > 2, Button::new("−Joint"))
+                .clicked()
+            {
+                ev_edit_joint_count.write(EditJointCount::Pop);
+            }
+        });
+        if ui
+            .button("Reset Camera")
+            .clicked()
+        {
+            ev_reset_camera.write_default();
+        }
+        if ui
+            .button("Clear Constraints")
+            .clicked()
+        {
+            chain.0.clear_constraints();
+            ui_state.lock_ground = chain.0.lock_ground;
+            ui_state.lock_fantasy_ground = chain.0.limb.as_ref().unwrap().lock_ground;
+        }
+        if ui
+            .button("Set as Home")
+            .clicked()
+        {
+            chain.0.set_home();
+        }
+        if ui
+            .button("Export DOT")
+            .clicked()
+        {
+            if let Err(err) = std::fs::write("chain.dot", chain.get(limb_state_ro.get()).to_dot()) {
+                error!("Could not export chain.dot: {err}");
+            }
+        }
+        if ui
+            .button("Export RON")
+            .clicked()
+        {
+            if let Err(err) = FabrikChain::export_transforms_ron(transform_history.0.make_contiguous(), "motion.ron") {
+                error!("Could not export motion.ron: {err}");
+            }
+        }
+        if ui
+            .button("Export Bincode")
+            .clicked()
+        {
+            if let Err(err) = chain.0.save_bincode("chain.bin") {
+                error!("Could not export chain.bin: {err}");
+            }
+        }
+        if ui
+            .button("Export SVG")
+            .clicked()
+        {
+            if let Err(err) = chain.get(limb_state_ro.get()).to_svg("chain.svg", Vec3::Z) {
+                error!("Could not export chain.svg: {err}");
+            }
+        }
+        if ui
+            .add_enabled(!velocity_display.0.is_empty(), Button::new("Export Plot PNG"))
+            .clicked()
+        {
+            if let Err(err) = plot_export::export_history_png(&velocity_display.0, "velocity.png") {
+                error!("Could not export velocity.png: {err}");
+            }
+        }
+        #[cfg(debug_assertions)]
+        if ui
+            .button("Export Solve Trace")
+            .clicked()
+        {
+            let trace = chain.get(limb_state_ro.get()).clone().solve_traced(10);
+            let result = ron::ser::to_string_pretty(&trace, ron::ser::PrettyConfig::default())
+                .map_err(std::io::Error::other)
+                .and_then(|contents| std::fs::write("trace.ron", contents));
+            if let Err(err) = result {
+                error!("Could not export trace.ron: {err}");
+            }
+        }
         if ui
             .checkbox(&mut ui_state.lock_ground, "Lock Ground")
             .changed()
         {
             chain.0.lock_ground = ui_state.lock_ground;
-            chain.0.limb.as_mut().unwrap().lock_ground = ui_state.lock_ground;
+        }
+        if ui
+            .checkbox(&mut ui_state.lock_fantasy_ground, "Lock Fantasy Ground")
+            .changed()
+        {
+            chain.0.limb.as_mut().unwrap().lock_ground = ui_state.lock_fantasy_ground;
         }
         
+        ui.checkbox(&mut chain.get_mut(limb_state_ro.get()).avoid_self_collision, "Avoid Self-Collision");
+        ui.checkbox(&mut chain.get_mut(limb_state_ro.get()).prevent_flips, "Prevent Elbow Flips");
+        ui.checkbox(&mut chain.get_mut(limb_state_ro.get()).warm_start, "Warm-Start Cache");
+        ui.checkbox(&mut chain.get_mut(limb_state_ro.get()).equalize_velocity, "Equalize Joint Velocity");
+        ui.checkbox(&mut chain.get_mut(limb_state_ro.get()).debug_solve, "Debug Solve");
+        ui.checkbox(&mut ui_state.show_frames, "Show Joint Frames");
+        ui.checkbox(&mut ui_state.subdivide, "Subdivide (click a segment)");
+        ui.checkbox(&mut ui_state.anchor_on_click, "Anchor (click the floor)");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut ui_state.show_passes, "Show Passes");
+            if ui.button("Step Half-Pass").clicked() {
+                chain.get_mut(limb_state_ro.get()).step_half_pass();
+                ev_sync_transforms.write_default();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut ui_state.fixed_solve, "Fixed Solve Rate");
+            ui.add(
+                DragValue::new(&mut ui_state.solve_hz)
+                    .range(1.0..=240.0)
+                    .suffix(" Hz")
+            );
+        });
+        ui.horizontal(|ui| {
+            let mut snap = ui_state.snap_grid.is_some();
+            if ui.checkbox(&mut snap, "Snap to Grid").changed() {
+                ui_state.snap_grid = snap.then_some(0.25);
+            }
+            if let Some(spacing) = ui_state.snap_grid.as_mut() {
+                ui.add(
+                    DragValue::new(spacing)
+                        .speed(0.01)
+                        .range(0.01..=5.0)
+                );
+            }
+        });
+        ui.add(
+            Slider::new(&mut ui_state.drag_smoothing, 0.0..=0.95)
+                .text("Drag Smoothing")
+        );
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut ui_state.animate, "Animate");
+            ui.add_enabled(
+                ui_state.animate,
+                Slider::new(&mut ui_state.animation_speed, 0.5..=20.0).text("Animation Speed")
+            );
+        });
+        
+        ui.add(
+            Slider::new(&mut chain.get_mut(limb_state_ro.get()).prediction_time, 0.0..=0.5)
+                .text("Prediction Time")
+        );
+        
+        ui.add(
+            Slider::new(&mut chain.get_mut(limb_state_ro.get()).fk_ik_blend, 0.0..=1.0)
+                .text("FK/IK Blend")
+        );
+        ui.horizontal(|ui| {
+            let tool_offset = &mut chain.get_mut(limb_state_ro.get()).tool_offset;
+            ui.label("Tool Offset");
+            ui.add(DragValue::new(&mut tool_offset.x).speed(0.01));
+            ui.add(DragValue::new(&mut tool_offset.y).speed(0.01));
+            ui.add(DragValue::new(&mut tool_offset.z).speed(0.01));
+        });
+        ui.horizontal(|ui| {
+            let pole_target = &mut chain.get_mut(limb_state_ro.get()).pole_target;
+            let mut has_pole = pole_target.is_some();
+            if ui.checkbox(&mut has_pole, "Pole Target").changed() {
+                *pole_target = has_pole.then_some(Vec3::Z);
+            }
+            if let Some(pole) = pole_target.as_mut() {
+                ui.add(DragValue::new(&mut pole.x).speed(0.01));
+                ui.add(DragValue::new(&mut pole.y).speed(0.01));
+                ui.add(DragValue::new(&mut pole.z).speed(0.01));
+            }
+        });
+        
+        ui.horizontal(|ui| {
+            for theme in Theme::iter() {
+                if ui
+                    .radio_value(&mut ui_state.theme, theme, theme.to_string())
+                    .clicked()
+                {
+                    if let Err(err) = theme.save(THEME_FILE) {
+                        error!("Could not save {THEME_FILE}: {err}");
+                    }
+                }
+            }
+        });
+        
+        ComboBox::from_label("Chain Template")
+            .selected_text(ui_state.chain_template.as_deref().unwrap_or("Default"))
+            .show_ui(ui, |ui| {
+                for path in chain_templates.0.iter() {
+                    let name = templates::template_name(path);
+                    if ui
+                        .selectable_label(ui_state.chain_template.as_ref() == Some(&name), &name)
+                        .clicked()
+                    {
+                        ev_load_template.write(LoadTemplate(path.clone()));
+                        ui_state.chain_template = Some(name);
+                    }
+                }
+            });
+        
         for possible_mode in LimbState::iter() {
             let name = possible_mode.to_string();
             if ui
@@ -425,40 +1697,762 @@ fn display_ui(
             }
         }
         
+        ComboBox::from_label("Pose Discrepancy")
+            .selected_text(format!("{:?}", ui_state.pose_discrepancy))
+            .show_ui(ui, |ui| {
+                for discrepancy in PoseDiscrepancy::iter() {
+                    ui.selectable_value(&mut ui_state.pose_discrepancy, discrepancy, format!("{discrepancy:?}"));
+                }
+            });
+        let solver = &mut chain.get_mut(limb_state_ro.get()).solver;
+        ComboBox::from_label("Solver")
+            .selected_text(format!("{solver:?}"))
+            .show_ui(ui, |ui| {
+                for kind in SolverKind::iter() {
+                    ui.selectable_value(solver, kind, format!("{kind:?}"));
+                }
+            });
+        ui.horizontal(|ui| {
+            let ground_height = &mut chain.get_mut(limb_state_ro.get()).motion_heuristics.ground_height;
+            let mut has_ground = ground_height.is_some();
+            if ui.checkbox(&mut has_ground, "Ground Plane").changed() {
+                *ground_height = has_ground.then_some(0.0);
+            }
+            if let Some(height) = ground_height.as_mut() {
+                ui.add(DragValue::new(height).speed(0.01));
+            }
+        });
+        ui.label(format!(
+            "Discrepancy: {:?} | Mode: {:?}",
+            ui_state.pose_discrepancy,
+            ui_state.kinematics_mode
+        ));
+        if last_solve.converged() {
+            let iterations = last_solve.0.as_ref().map_or(0, |report| report.iterations);
+            ui.label(format!("Converged in {iterations} iterations"));
+        } else {
+            ui.label("Not Converged");
+        }
+        if !last_solve.reachable() {
+            ui.colored_label(Color32::RED, "Target out of reach");
+        }
+        if let Some(report) = last_solve.0.as_ref().filter(|report| !report.error_history.is_empty()) {
+            ui.label(format!("Max Joint Delta: {:.4}", report.max_joint_delta));
+            let errors: PlotPoints = report.error_history
+                .iter()
+                .enumerate()
+                .map(|(x, error)| [x as f64, *error as f64])
+                .collect();
+            Plot::new("solve_error")
+                .height(80.0)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| plot_ui.line(Line::new("Error", errors)));
+        }
+        ui.horizontal(|ui| {
+            let frame_ms = solve_budget.history.back().copied().unwrap_or(0.0);
+            ui.monospace(format!("Solve: {frame_ms:.3} ms"));
+            let sparkline: PlotPoints = solve_budget.history
+                .iter()
+                .enumerate()
+                .map(|(x, ms)| [x as f64, *ms as f64])
+                .collect();
+            Plot::new("solve_budget")
+                .height(24.0)
+                .width(120.0)
+                .show_axes(false)
+                .show_grid(false)
+                .allow_drag(false)
+                .allow_zoom(false)
+                .allow_scroll(false)
+                .show(ui, |plot_ui| plot_ui.line(Line::new("Solve ms", sparkline)));
+        });
+        
+        if ui
+            .button("FK/IK Round-trip Test")
+            .clicked()
+        {
+            ui_state.round_trip_residual = Some(
+                chain
+                    .get(limb_state_ro.get())
+                    .round_trip_residual(Vec3::new(0.0, 0.1, 0.0), 10)
+            );
+        }
+        if let Some(residual) = ui_state.round_trip_residual {
+            if residual <= ROUND_TRIP_TOLERANCE {
+                ui.colored_label(Color32::GREEN, format!("Round-trip PASS (residual {residual:.5})"));
+            } else {
+                ui.colored_label(Color32::RED, format!("Round-trip FAIL (residual {residual:.5})"));
+            }
+        }
+        
         ui.separator();
         
-        let mut velocities = Vec::new();
-        if let Some(first_len) = velocity_display.0.first().map(|x| x.len()) {
-            for _ in 0..first_len {
-                velocities.push(Vec::new());
-            }
-            for x in 0..velocity_display.0.len() {
-                for y in 0..velocity_display.0[x].len() {
-                    let new_point = [x as f64, velocity_display.0[x][y] as f64];
-                    match velocities.get_mut(y) {
-                        Some(y_ptr) => {
-                            y_ptr.push(new_point);
+        ui.collapsing("Pose Library", |ui| {
+            ui.horizontal(|ui| {
+                ui.text_edit_singleline(&mut ui_state.pose_name);
+                if ui
+                    .add_enabled(!ui_state.pose_name.is_empty(), Button::new("Save Pose"))
+                    .clicked()
+                {
+                    pose_library.store(&ui_state.pose_name, chain.get(limb_state_ro.get()));
+                    if let Err(err) = pose_library.save(POSE_LIBRARY_FILE) {
+                        error!("Could not save {POSE_LIBRARY_FILE}: {err}");
+                    }
+                }
+            });
+            ui.checkbox(&mut ui_state.interpolate_recall, "Interpolate Recall");
+            
+            let names: Vec<String> = pose_library.0.keys().cloned().collect();
+            for name in names {
+                if ui.button(format!("Recall {name}")).clicked() {
+                    let limb = chain.get_mut(limb_state_ro.get());
+                    if let Some(joints) = pose_library.recall(&name, limb).map(<[Vec3]>::to_vec) {
+                        if ui_state.interpolate_recall {
+                            pose_recall.0 = Some(joints);
+                        } else {
+                            limb.joints = joints;
+                            limb.recalculate_segments();
+                            ev_sync_transforms.write_default();
                         }
-                        None => {
-                            velocities.push(vec![new_point]);
+                    } else {
+                        warn!("Pose {name} does not match the current joint count");
+                    }
+                }
+            }
+        });
+        
+        ui.collapsing("Joints", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            for i in 0..limb.joints.len() {
+                ui.horizontal(|ui| {
+                    ui.monospace(format_joint(i, limb.joint_label(i), limb.joints[i]));
+                    let mut frozen = limb.is_frozen(i);
+                    if ui.checkbox(&mut frozen, "Frozen").changed() {
+                        limb.set_frozen(i, frozen);
+                    }
+                    let mut hinged = limb.hinge_axis(i).is_some();
+                    if i + 1 < limb.joints.len() && ui.checkbox(&mut hinged, "Hinge").changed() {
+                        let axis = hinged.then(|| limb.bend_normal());
+                        limb.set_hinge(i, axis);
+                    }
+                });
+            }
+        });
+        
+        ui.collapsing("Targets", |ui| {
+            let reached = chain.get(limb_state_ro.get()).targets_reached();
+            if reached.is_empty() {
+                ui.label("NO TARGETS");
+            }
+            for (index, reached) in reached {
+                let color = if reached { Color32::GREEN } else { Color32::RED };
+                ui.horizontal(|ui| {
+                    ui.colored_label(color, "●");
+                    ui.label(format!("Joint {index}"));
+                });
+            }
+            
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("End Effector");
+                ui.add(DragValue::new(&mut ui_state.ee_target.x).speed(0.01));
+                ui.add(DragValue::new(&mut ui_state.ee_target.y).speed(0.01));
+                ui.add(DragValue::new(&mut ui_state.ee_target.z).speed(0.01));
+            });
+            ui.horizontal(|ui| {
+                let limb = chain.get_mut(limb_state_ro.get());
+                if ui.button("Use Current").clicked() {
+                    ui_state.ee_target = limb.get_ee();
+                }
+                if ui.button("Apply").clicked() {
+                    let tip = limb.joints.len() - 1;
+                    target_queue.0.push(TargetCommand::Set(tip, ui_state.ee_target));
+                    for (ctrl_ball, mut transform) in query_ctrl_ball.iter_mut() {
+                        if ctrl_ball.index == tip {
+                            transform.translation = ui_state.ee_target;
                         }
                     }
+                    ev_move_limb.write_default();
                 }
+            });
+        });
+        
+        ui.collapsing("Forward Kinematics", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            if ui
+                .checkbox(&mut ui_state.forward_kinematics, "Drive Joint Angles")
+                .changed()
+            {
+                ui_state.kinematics_mode = if ui_state.forward_kinematics {
+                    KinematicsMode::ForwardKinematics
+                } else {
+                    KinematicsMode::InverseKinematics
+                };
+                limb.recalculate_angles();
+            }
+            if !ui_state.forward_kinematics {
+                return;
+            }
+            if limb.angles.len() + 2 != limb.joints.len() {
+                limb.recalculate_angles();
             }
             
-            let lines = velocities
+            let mut changed = false;
+            for i in 1..limb.joints.len() - 1 {
+                let (min, max) = limb.angle_limit(i);
+                let mut angle_deg = limb.angles[i - 1].to_degrees();
+                if ui
+                    .add(
+                        Slider::new(&mut angle_deg, min.to_degrees()..=max.to_degrees())
+                            .suffix("°")
+                            .text(format!("Joint {i}"))
+                    )
+                    .changed()
+                {
+                    limb.angles[i - 1] = angle_deg.to_radians();
+                    changed = true;
+                }
+            }
+            if changed {
+                limb.apply_forward_kinematics();
+                ev_sync_transforms.write_default();
+            }
+        });
+        
+        ui.collapsing("Angle Limits", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            for i in 1..limb.joints.len().saturating_sub(1) {
+                let (min, max) = limb.angle_limit(i);
+                let (mut min_deg, mut max_deg) = (min.to_degrees(), max.to_degrees());
+                ui.horizontal(|ui| {
+                    ui.label(format!("Joint {i}"));
+                    let changed = ui.add(DragValue::new(&mut min_deg).range(0.0..=max_deg).suffix("°")).changed()
+                        | ui.add(DragValue::new(&mut max_deg).range(min_deg..=180.0).suffix("°")).changed();
+                    if changed {
+                        limb.set_angle_limit(i, (min_deg.to_radians(), max_deg.to_radians()));
+                    }
+                });
+            }
+        });
+        
+        ui.collapsing("Braces", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            let max_index = limb.joints.len() - 1;
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut ui_state.brace.0).range(0..=max_index));
+                ui.add(DragValue::new(&mut ui_state.brace.1).range(0..=max_index));
+                let (a, b) = ui_state.brace;
+                if ui
+                    .add_enabled(a != b && a <= max_index && b <= max_index, Button::new("Add Brace"))
+                    .clicked()
+                {
+                    let distance = limb.joints[a].distance(limb.joints[b]);
+                    limb.distance_constraints.push((a, b, distance));
+                }
+            });
+            let mut removed = None;
+            for (i, (a, b, distance)) in limb.distance_constraints.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("{a} - {b}"));
+                    ui.add(DragValue::new(distance).speed(0.01).range(0.0..=100.0));
+                    if ui.button("Remove").clicked() {
+                        removed = Some(i);
+                    }
+                });
+            }
+            if let Some(i) = removed {
+                limb.distance_constraints.remove(i);
+            }
+        });
+        
+        ui.collapsing("Sensitivity", |ui| {
+            let bars = chain
+                .get(limb_state_ro.get())
+                .sensitivity(Vec3::Y * SENSITIVITY_DELTA)
                 .into_iter()
-                .map(|x| Line::new("Plot #1", PlotPoints::new(x)));
-            
-            Plot::new("velocity")
+                .enumerate()
+                .map(|(i, contribution)| Bar::new(i as f64, (contribution / SENSITIVITY_DELTA) as f64))
+                .collect();
+            Plot::new("sensitivity")
                 .view_aspect(2.0)
                 .show(ui, |plot_ui| {
-                    for line in lines {
-                        plot_ui.line(line);
-                    }
+                    plot_ui.bar_chart(BarChart::new("Joint Response", bars));
                 });
-        } else {
-            ui.label("NO DATA");
+        });
+        
+        ui.collapsing("Segment Lengths", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            for i in 0..limb.lengths.len() {
+                let mut length = limb.lengths[i];
+                if ui
+                    .add(
+                        DragValue::new(&mut length)
+                            .speed(0.01)
+                            .range(0.05..=10.0)
+                            .prefix(format!("Segment {i}: "))
+                    )
+                    .changed()
+                {
+                    limb.set_segment_length(i, length);
+                    ev_rebuild_segment_mesh.write(RebuildSegmentMesh(i));
+                    ev_recompute.write_default();
+                }
+            }
+        });
+        
+        ui.collapsing("Segment Masses", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            let mut changed = false;
+            for (i, mass) in limb.segment_masses.iter_mut().enumerate() {
+                changed |= ui
+                    .add(
+                        DragValue::new(mass)
+                            .speed(0.05)
+                            .range(0.05..=10.0)
+                            .prefix(format!("Segment {i}: "))
+                    )
+                    .changed();
+            }
+            if changed {
+                limb.recalculate_segments();
+                ev_sync_transforms.write_default();
+            }
+        });
+        
+        ui.collapsing("Rest Pose", |ui| {
+            let limb = chain.get_mut(limb_state_ro.get());
+            if ui.button("Set Rest Pose").clicked() {
+                limb.rest_angles = limb.angles.clone();
+            }
+            ui.checkbox(&mut ui_state.show_rest_deviation, "Show Deviation");
+            
+            let deviations = limb.rest_deviations();
+            if deviations.is_empty() {
+                ui.label("NO REST POSE");
+            }
+            for (i, deviation) in deviations.into_iter().enumerate() {
+                let [r, g, b] = deviation_rgb(deviation);
+                ui.colored_label(
+                    Color32::from_rgb(r, g, b),
+                    format!("Joint {}: {:.1}°", i + 1, deviation.to_degrees())
+                );
+            }
+        });
+        
+        ui.separator();
+        
+        ui.checkbox(&mut ui_state.downsample_plots, "Downsample Plots");
+        ui.horizontal(|ui| {
+            ui.label("Angular Velocity");
+            if ui
+                .add_enabled(!velocity_display.0.is_empty(), Button::new("Export CSV"))
+                .clicked()
+            {
+                if let Err(err) = plot_export::export_history_csv(&velocity_display.0, "velocity.csv") {
+                    error!("Could not export velocity.csv: {err}");
+                }
+            }
+        });
+        history_plot(ui, "velocity", &velocity_display.0, ui_state.downsample_plots);
+        ui.label("Angular Acceleration");
+        history_plot(ui, "acceleration", &acceleration_display.0, ui_state.downsample_plots);
+        ui.label("Segment Linear Velocity");
+        history_plot(ui, "segment_velocity", &segment_velocity_display.0, ui_state.downsample_plots);
+        
+        ui.separator();
+        
+        let limb = chain.get(limb_state_ro.get());
+        let manipulability = limb.manipulability();
+        let reach: f32 = limb.lengths.iter().sum();
+        ui.label(format!("Effective DOF: {}", limb.effective_dof()));
+        let max_delta = chain.0
+            .diff(chain.0.limb.as_ref().unwrap())
+            .into_iter()
+            .map(Vec3::length)
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((joint, delta)) = max_delta {
+            ui.label(format!("Real vs Fantasy: max delta {delta:.3} at joint {joint}"));
         }
+        ui.label("Manipulability");
+        ui.add(
+            ProgressBar::new((manipulability / reach.powi(3)).clamp(0.0, 1.0))
+                .text(format!("{manipulability:.3}"))
+        );
+        
+        ui.separator();
+        
+        ui.add(
+            DragValue::new(&mut ui_state.tip_load)
+                .speed(0.1)
+                .prefix("Tip Load: ")
+        );
+        let bars = chain
+            .get(limb_state_ro.get())
+            .static_torques(Vec3::NEG_Y * ui_state.tip_load)
+            .into_iter()
+            .enumerate()
+            .map(|(i, torque)| Bar::new(i as f64, torque as f64))
+            .collect();
+        
+        Plot::new("torques")
+            .view_aspect(2.0)
+            .show(ui, |plot_ui| {
+                plot_ui.bar_chart(BarChart::new("Joint Torques", bars));
+            });
     });
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::{message::Messages, system::RunSystemOnce};
+    use bevy::time::{TimePlugin, TimeUpdateStrategy};
+    
+    fn headless_app(chain: Option<FabrikChain>) -> App {
+        let mut app = App::new();
+        app
+            .add_message::<RecomputeLimb>()
+            .add_message::<GizmoUpdate>()
+            .add_message::<MoveLimb>()
+            .add_message::<SyncTransform>()
+            .insert_resource(State::new(LimbState::RealLimb))
+            .init_resource::<UiState>()
+            .init_resource::<Paused>()
+            .init_resource::<LastSolve>()
+            .init_resource::<SolveBudget>()
+            .init_resource::<AnimationGoal>()
+            .init_resource::<TargetQueue>()
+            .init_resource::<DesiredTargets>();
+        app.world_mut().spawn((
+            VelocityDisplay::default(),
+            SegmentVelocityDisplay::default(),
+            AccelerationDisplay::default(),
+            TransformHistory::default()
+        ));
+        if let Some(chain) = chain {
+            app.world_mut().spawn(LimbData(chain));
+        }
+        app
+    }
+    
+    fn limb_joints(app: &mut App) -> Vec<Vec3> {
+        let mut query = app.world_mut().query::<&LimbData>();
+        query.single(app.world()).unwrap().0.joints.clone()
+    }
+    
+    fn placeholder_assets() -> LimbAssets {
+        LimbAssets {
+            control_ball_mesh: Handle::default(),
+            ball_mesh: Handle::default(),
+            fantasy_ball_mesh: Handle::default(),
+            material: Handle::default(),
+            fantasy_material: Handle::default(),
+            transculent_material: Handle::default(),
+            collision_material: Handle::default(),
+            target_mesh: Handle::default(),
+            target_material: Handle::default(),
+        }
+    }
+    
+    fn bent_target_chain() -> FabrikChain {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0], MotionHueristics::default());
+        chain.targets = vec![(2, Vec3::new(1.0, 1.0, 0.0))];
+        chain
+    }
+    
+    #[test]
+    fn messages_during_pause_leave_the_chain_alone() {
+        let mut app = headless_app(Some(bent_target_chain()));
+        app.add_systems(Update, recompute_limb.run_if(not_paused).run_if(on_message::<RecomputeLimb>));
+        let before = limb_joints(&mut app);
+        
+        app.world_mut().resource_mut::<Paused>().0 = true;
+        app.world_mut().write_message(RecomputeLimb);
+        app.update();
+        assert_eq!(limb_joints(&mut app), before);
+        
+        app.world_mut().resource_mut::<Paused>().0 = false;
+        app.world_mut().write_message(RecomputeLimb);
+        app.update();
+        assert_ne!(limb_joints(&mut app), before);
+    }
+    
+    #[test]
+    fn solve_rate_is_written_only_when_it_changes() {
+        let mut world = World::new();
+        world.insert_resource(UiState::default());
+        world.insert_resource(Time::<Fixed>::from_duration(solve_timestep(60.0)));
+        world.clear_trackers();
+        
+        world.run_system_once(apply_solve_rate).unwrap();
+        assert!(!world.resource_ref::<Time<Fixed>>().is_changed());
+        
+        world.resource_mut::<UiState>().solve_hz = 30.0;
+        world.clear_trackers();
+        world.run_system_once(apply_solve_rate).unwrap();
+        assert!(world.resource_ref::<Time<Fixed>>().is_changed());
+        assert_eq!(world.resource::<Time<Fixed>>().timestep(), solve_timestep(30.0));
+    }
+    
+    #[test]
+    fn fixed_solve_waits_for_a_pending_request() {
+        let mut world = World::new();
+        world.init_resource::<PendingSolve>();
+        assert!(!world.run_system_once(pending_solve).unwrap());
+        
+        world.run_system_once(mark_pending_solve).unwrap();
+        assert!(world.run_system_once(pending_solve).unwrap());
+        
+        world.run_system_once(clear_pending_solve).unwrap();
+        assert!(!world.run_system_once(pending_solve).unwrap());
+    }
+    
+    #[test]
+    fn fixed_solve_runs_once_per_tick() {
+        let timestep = solve_timestep(60.0);
+        let mut app = headless_app(Some(bent_target_chain()));
+        app
+            .add_plugins(TimePlugin)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(timestep))
+            .insert_resource(Time::<Fixed>::from_duration(timestep))
+            .init_resource::<PendingSolve>()
+            .init_resource::<PreviousSolvedPose>()
+            .add_systems(
+                FixedUpdate, 
+                (
+                    capture_solved_pose,
+                    (recompute_limb, clear_pending_solve)
+                        .chain()
+                        .run_if(not_paused)
+                        .run_if(not(forward_kinematics))
+                        .run_if(pending_solve),
+                )
+                    .chain()
+                    .run_if(fixed_solve)
+            );
+        app.world_mut().resource_mut::<UiState>().fixed_solve = true;
+        
+        let mut solves = Vec::new();
+        for requested in [true, true, true, false, true] {
+            if requested {
+                app.world_mut().run_system_once(mark_pending_solve).unwrap();
+            }
+            app.update();
+            solves.push(app.world_mut().resource_mut::<Messages<SyncTransform>>().drain().count());
+        }
+        // The first update only starts the clock, so the request waits for the next tick.
+        assert_eq!(solves, [0, 1, 1, 0, 1]);
+    }
+    
+    #[test]
+    fn control_balls_follow_the_interpolated_pose() {
+        let mut world = World::new();
+        let mut time = Time::<Fixed>::from_duration(Duration::from_secs(1));
+        time.accumulate_overstep(Duration::from_millis(500));
+        world.insert_resource(time);
+        world.insert_resource(State::new(LimbState::RealLimb));
+        world.insert_resource(PreviousSolvedPose {
+            joints: vec![Vec3::ZERO, Vec3::Y, Vec3::Y * 2.0],
+            segments: Vec::new(),
+        });
+        world.spawn(LimbData(FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0], MotionHueristics::default())));
+        let ctrl_ball = world.spawn((ControlBall { index: 1 }, Transform::from_translation(Vec3::X))).id();
+        
+        world.run_system_once(interpolate_solved_pose).unwrap();
+        let translation = world.get::<Transform>(ctrl_ball).unwrap().translation;
+        assert!(translation.distance(Vec3::new(0.5, 0.5, 0.0)) < 1e-5, "{translation}");
+    }
+    
+    #[test]
+    fn large_drag_deltas_are_approached_incrementally() {
+        let mut app = headless_app(Some(bent_target_chain()));
+        let desired = Vec3::new(0.0, 2.0, 0.0);
+        app.world_mut().resource_mut::<UiState>().drag_smoothing = 0.5;
+        app.world_mut().resource_mut::<DesiredTargets>().0 = vec![(2, desired)];
+        app.add_systems(Update, (ease_targets, apply_target_queue).chain());
+        
+        let mut distances = Vec::new();
+        for _ in 0..4 {
+            app.update();
+            let mut query = app.world_mut().query::<&LimbData>();
+            let target = query.single(app.world()).unwrap().0.targets[0].1;
+            distances.push(target.distance(desired));
+        }
+        assert!(distances[0] > DRAG_SETTLE_DISTANCE, "{distances:?}");
+        assert!(distances.windows(2).all(|pair| pair[1] < pair[0]), "{distances:?}");
+    }
+    
+    #[test]
+    fn last_solve_reflects_the_most_recent_outcome() {
+        let mut app = headless_app(Some(bent_target_chain()));
+        app.add_systems(Update, recompute_limb.run_if(on_message::<RecomputeLimb>));
+        assert!(!app.world().resource::<LastSolve>().converged());
+        
+        app.world_mut().write_message(RecomputeLimb);
+        app.update();
+        assert!(app.world().resource::<LastSolve>().converged());
+        
+        let mut query = app.world_mut().query::<&mut LimbData>();
+        query.single_mut(app.world_mut()).unwrap().0.targets = vec![(2, Vec3::X * 10.0)];
+        app.world_mut().write_message(RecomputeLimb);
+        app.update();
+        assert!(!app.world().resource::<LastSolve>().converged());
+    }
+    
+    #[test]
+    fn joint_readout_reflects_the_current_joints() {
+        let mut chain = bent_target_chain();
+        chain.joint_labels = vec![None, Some("elbow".to_string())];
+        chain.joints[2] = Vec3::new(1.5, -0.25, 2.0);
+        
+        let readout: Vec<String> = (0..chain.joints.len())
+            .map(|i| format_joint(i, chain.joint_label(i), chain.joints[i]))
+            .collect();
+        assert_eq!(readout, vec![
+            "0: 0.000, 0.000, 0.000".to_string(),
+            "1 (elbow): 1.000, 0.000, 0.000".to_string(),
+            "2: 1.500, -0.250, 2.000".to_string(),
+        ]);
+    }
+    
+    #[test]
+    fn targets_near_grid_points_snap_to_them() {
+        assert_eq!(snap_to_grid(Vec3::new(0.98, 2.03, -0.51), 0.5), Vec3::new(1.0, 2.0, -0.5));
+        assert_eq!(snap_to_grid(Vec3::new(0.24, 0.0, 0.26), 0.5), Vec3::new(0.0, 0.0, 0.5));
+    }
+    
+    #[test]
+    fn mild_divergence_reports_forward_kinematics() {
+        let mut app = headless_app(Some(bent_target_chain()));
+        app.world_mut().resource_mut::<UiState>().pose_discrepancy = PoseDiscrepancy::MildDivergence;
+        app.add_systems(Update, recompute_limb.run_if(on_message::<RecomputeLimb>));
+        
+        app.world_mut().write_message(RecomputeLimb);
+        app.update();
+        assert_eq!(app.world().resource::<UiState>().kinematics_mode, KinematicsMode::ForwardKinematics);
+    }
+    
+    #[test]
+    fn clicks_anchor_the_nearest_joint() {
+        let joints = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::new(2.0, 1.0, 0.0)];
+        assert_eq!(nearest_joint(&joints, Vec3::new(0.9, -0.2, 0.3)), Some(1));
+        assert_eq!(nearest_joint(&joints, Vec3::new(2.0, 1.2, 0.5)), Some(3));
+        assert_eq!(nearest_joint(&joints, Vec3::new(-5.0, 0.0, 0.0)), Some(0));
+        assert_eq!(nearest_joint(&[], Vec3::ZERO), None);
+    }
+    
+    #[test]
+    fn systems_skip_an_empty_scene() {
+        let mut app = headless_app(None);
+        app
+            .init_resource::<Time>()
+            .init_resource::<Time<Fixed>>()
+            .init_resource::<PreviousSolvedPose>()
+            .init_resource::<PoseRecall>()
+            .add_systems(Update, (
+                ease_targets,
+                move_limb,
+                apply_target_queue,
+                recompute_limb,
+                animate_limb,
+                pose_library::step_pose_recall,
+                sync_ball_transform,
+                sync_ctrl_ball_transform,
+                sync_segment_transform,
+                capture_solved_pose,
+                interpolate_solved_pose,
+            ).chain());
+        
+        let world = app.world_mut();
+        world.resource_mut::<DesiredTargets>().0 = vec![(1, Vec3::Y)];
+        world.resource_mut::<TargetQueue>().0 = vec![TargetCommand::Set(1, Vec3::Y)];
+        world.resource_mut::<AnimationGoal>().0 = Some(vec![Vec3::ZERO, Vec3::Y]);
+        world.resource_mut::<PoseRecall>().0 = Some(vec![Vec3::ZERO, Vec3::Y]);
+        world.write_message(GizmoUpdate::Drag { entity: Entity::PLACEHOLDER });
+        world.write_message(RecomputeLimb);
+        
+        app.update();
+        app.update();
+    }
+    
+    #[test]
+    fn target_markers_follow_the_targets() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0, Vec3::X * 3.0], MotionHueristics::default());
+        chain.targets = vec![(1, Vec3::Y), (2, Vec3::new(1.0, 1.0, 0.0)), (3, Vec3::new(2.0, 1.0, 0.0))];
+        let mut app = headless_app(Some(chain));
+        app
+            .insert_resource(placeholder_assets())
+            .add_systems(Update, sync_target_markers);
+        let marker_count = |app: &mut App| app.world_mut().query::<&TargetMarker>().iter(app.world()).count();
+        
+        app.update();
+        assert_eq!(marker_count(&mut app), 3);
+        app.update();
+        assert_eq!(marker_count(&mut app), 3);
+        
+        let mut query = app.world_mut().query::<&mut LimbData>();
+        query.single_mut(app.world_mut()).unwrap().0.targets.truncate(1);
+        app.update();
+        assert_eq!(marker_count(&mut app), 1);
+        
+        let mut query = app.world_mut().query::<(&TargetMarker, &Transform)>();
+        let (marker, transform) = query.single(app.world()).unwrap();
+        assert_eq!(marker.index, 0);
+        assert_eq!(transform.translation, Vec3::Y);
+    }
+    
+    #[test]
+    fn downsampling_keeps_the_extrema() {
+        let mut points: Vec<[f64; 2]> = (0..10_000).map(|i| [i as f64, (i as f64 * 0.01).sin()]).collect();
+        points[1234][1] = 5.0;
+        points[8765][1] = -5.0;
+        
+        let downsampled = downsample_min_max(points.clone(), 200);
+        assert!(downsampled.len() <= 400 && downsampled.len() < points.len(), "{}", downsampled.len());
+        assert!(downsampled.contains(&[1234.0, 5.0]));
+        assert!(downsampled.contains(&[8765.0, -5.0]));
+        assert!(downsampled.windows(2).all(|pair| pair[0][0] <= pair[1][0]));
+        
+        assert_eq!(downsample_min_max(points[..300].to_vec(), 200).len(), 300);
+    }
+    
+    #[test]
+    fn popping_a_joint_respawns_before_syncing() {
+        let mut app = headless_app(Some(FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0, Vec3::X * 3.0], MotionHueristics::default())));
+        app
+            .add_message::<EditJointCount>()
+            .insert_resource(placeholder_assets())
+            .init_resource::<Assets<Mesh>>()
+            .configure_sets(Update, RespawnLimb
+                .before(sync_ball_transform)
+                .before(sync_ctrl_ball_transform)
+                .before(sync_segment_transform))
+            .add_systems(Update, (
+                edit_joint_count.run_if(on_message::<EditJointCount>).in_set(RespawnLimb),
+                sync_ball_transform.run_if(on_message::<SyncTransform>),
+                sync_ctrl_ball_transform.run_if(on_message::<SyncTransform>),
+                sync_segment_transform.run_if(on_message::<SyncTransform>),
+            ));
+        app.world_mut()
+            .run_system_once(|mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, limb_assets: Res<LimbAssets>, query_chain: Query<&LimbData>| {
+                spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &query_chain.single().unwrap().0);
+            })
+            .unwrap();
+        
+        app.world_mut().write_message(EditJointCount::Pop);
+        app.update();
+        assert_eq!(limb_joints(&mut app).len(), 3);
+        let world = app.world_mut();
+        assert_eq!(world.query::<&InnerBall>().iter(world).count(), 6);
+        assert_eq!(world.query::<&ControlBall>().iter(world).count(), 3);
+        assert_eq!(world.query::<&Segment>().iter(world).count(), 4);
+        
+        let mut query = world.query::<(&ControlBall, &Transform)>();
+        for (ball, transform) in query.iter(world) {
+            assert_eq!(transform.translation, Vec3::X * ball.index as f32);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+use std::{fmt::Write, io, path::Path};
+
+use image::{Rgb, RgbImage};
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+const MARGIN: u32 = 20;
+const BACKGROUND: Rgb<u8> = Rgb([255, 255, 255]);
+const AXIS: Rgb<u8> = Rgb([160, 160, 160]);
+const PALETTE: [Rgb<u8>; 6] = [
+    Rgb([31, 119, 180]),
+    Rgb([255, 127, 14]),
+    Rgb([44, 160, 44]),
+    Rgb([214, 39, 40]),
+    Rgb([148, 103, 189]),
+    Rgb([140, 86, 75]),
+];
+
+fn draw_line(image: &mut RgbImage, (x0, y0): (i64, i64), (x1, y1): (i64, i64), color: Rgb<u8>) {
+    let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+    let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+    let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+    loop {
+        if (0..image.width() as i64).contains(&x) && (0..image.height() as i64).contains(&y) {
+            image.put_pixel(x as u32, y as u32, color);
+        }
+        if x == x1 && y == y1 {
+            break;
+        }
+        let e2 = 2 * err;
+        if e2 >= dy {
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            err += dx;
+            y += sy;
+        }
+    }
+}
+
+pub fn render_history(history: &[Vec<f32>]) -> Option<RgbImage> {
+    let series = history.iter().map(Vec::len).max().filter(|&len| len > 0)?;
+    
+    let (min, max) = history
+        .iter()
+        .flatten()
+        .filter(|value| value.is_finite())
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), value| (min.min(*value), max.max(*value)));
+    let (min, max) = if min < max { (min, max) } else { (min - 1.0, min + 1.0) };
+    if !min.is_finite() {
+        return None;
+    }
+    
+    let (plot_width, plot_height) = ((WIDTH - 2 * MARGIN) as f32, (HEIGHT - 2 * MARGIN) as f32);
+    let to_pixel = |x: usize, value: f32| {
+        let x = MARGIN as f32 + x as f32 / (history.len() - 1).max(1) as f32 * plot_width;
+        let y = MARGIN as f32 + (max - value) / (max - min) * plot_height;
+        (x.round() as i64, y.round() as i64)
+    };
+    
+    let mut image = RgbImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+    let (left, right) = (MARGIN as i64, (WIDTH - MARGIN) as i64);
+    let (top, bottom) = (MARGIN as i64, (HEIGHT - MARGIN) as i64);
+    draw_line(&mut image, (left, top), (left, bottom), AXIS);
+    draw_line(&mut image, (left, bottom), (right, bottom), AXIS);
+    if min < 0.0 && max > 0.0 {
+        let (_, zero) = to_pixel(0, 0.0);
+        draw_line(&mut image, (left, zero), (right, zero), AXIS);
+    }
+    
+    for y in 0..series {
+        let color = PALETTE[y % PALETTE.len()];
+        let points: Vec<(i64, i64)> = history
+            .iter()
+            .enumerate()
+            .filter_map(|(x, sample)| sample.get(y).filter(|value| value.is_finite()).map(|value| to_pixel(x, *value)))
+            .collect();
+        for pair in points.windows(2) {
+            draw_line(&mut image, pair[0], pair[1], color);
+        }
+    }
+    Some(image)
+}
+
+pub fn export_history_png(history: &[Vec<f32>], path: impl AsRef<Path>) -> io::Result<()> {
+    let image = render_history(history)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no history to export"))?;
+    image.save(path).map_err(io::Error::other)
+}
+
+pub fn history_csv(history: &[Vec<f32>]) -> String {
+    let columns = history.iter().map(Vec::len).max().unwrap_or(0);
+    let mut csv = (1..=columns).map(|joint| format!("joint_{joint}")).collect::<Vec<_>>().join(",");
+    csv.push('\n');
+    for sample in history {
+        let row: Vec<String> = (0..columns)
+            .map(|column| sample.get(column).map_or_else(String::new, |value| value.to_string()))
+            .collect();
+        writeln!(csv, "{}", row.join(",")).unwrap();
+    }
+    csv
+}
+
+pub fn export_history_csv(history: &[Vec<f32>], path: impl AsRef<Path>) -> io::Result<()> {
+    std::fs::write(path, history_csv(history))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn exporting_history_writes_a_png() {
+        let history = vec![vec![0.0, 1.0], vec![0.5, -1.0], vec![1.0, 0.0]];
+        let path = std::env::temp_dir().join("robot_arm_velocity_plot.png");
+        export_history_png(&history, &path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(bytes[..8], [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n']);
+        
+        assert!(export_history_png(&[], &path).is_err());
+        assert!(export_history_png(&[Vec::new()], &path).is_err());
+    }
+    
+    #[test]
+    fn csv_rows_are_frames_and_columns_are_joints() {
+        let history = vec![vec![0.5, -1.0, 2.0], vec![0.25, 0.0], vec![1.0, 1.5, -0.75]];
+        assert_eq!(history_csv(&history), "joint_1,joint_2,joint_3\n0.5,-1,2\n0.25,0,\n1,1.5,-0.75\n");
+        assert_eq!(history_csv(&[]), "\n");
+    }
+}
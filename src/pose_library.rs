@@ -0,0 +1,112 @@
+use std::{collections::BTreeMap, io, path::Path};
+
+use bevy::prelude::*;
+
+use robot_arm::ik::FabrikChain;
+
+use crate::{LimbData, LimbState, SyncTransform, ease_toward};
+
+pub const POSE_LIBRARY_FILE: &str = "poses.ron";
+const RECALL_SMOOTHING: f32 = 0.85;
+const RECALL_SETTLE_DISTANCE: f32 = 1e-3;
+
+#[derive(Resource, Default)]
+pub struct PoseLibrary(pub BTreeMap<String, Vec<Vec3>>);
+
+impl PoseLibrary {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        ron::from_str(&contents)
+            .map(Self)
+            .map_err(io::Error::other)
+    }
+    
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(&self.0, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+    
+    pub fn store(&mut self, name: &str, chain: &FabrikChain) {
+        self.0.insert(name.to_string(), chain.joints.clone());
+    }
+    
+    pub fn recall(&self, name: &str, chain: &FabrikChain) -> Option<&[Vec3]> {
+        self.0
+            .get(name)
+            .filter(|joints| joints.len() == chain.joints.len() && joints.iter().all(|joint| joint.is_finite()))
+            .map(Vec::as_slice)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PoseRecall(pub Option<Vec<Vec3>>);
+
+pub fn load_pose_library(mut commands: Commands) {
+    let library = match PoseLibrary::load(POSE_LIBRARY_FILE) {
+        Ok(library) => library,
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Could not load {POSE_LIBRARY_FILE}: {err}");
+            }
+            PoseLibrary::default()
+        }
+    };
+    commands.insert_resource(library);
+}
+
+pub fn step_pose_recall(
+    mut query_chain: Query<&mut LimbData>,
+    mut pose_recall: ResMut<PoseRecall>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>,
+    limb_state: Res<State<LimbState>>
+) {
+    let Some(goal) = pose_recall.0.as_ref() else { return; };
+    
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    let limb = chain.get_mut(limb_state.get());
+    if limb.joints.len() != goal.len() {
+        pose_recall.0 = None;
+        return;
+    }
+    
+    let mut settled = true;
+    for (joint, goal) in limb.joints.iter_mut().zip(goal.iter()) {
+        *joint = ease_toward(*joint, *goal, RECALL_SMOOTHING);
+        if joint.distance(*goal) < RECALL_SETTLE_DISTANCE {
+            *joint = *goal;
+        } else {
+            settled = false;
+        }
+    }
+    limb.recalculate_segments();
+    if settled {
+        pose_recall.0 = None;
+    }
+    
+    ev_sync_transforms.write_default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use robot_arm::ik::MotionHueristics;
+    
+    #[test]
+    fn stored_poses_recall_unchanged() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)], MotionHueristics::default());
+        let mut library = PoseLibrary::default();
+        library.store("wave", &chain);
+        let stored = chain.joints.clone();
+        chain.joints[2] = Vec3::new(2.0, 0.0, 0.0);
+        
+        assert_eq!(library.recall("wave", &chain), Some(stored.as_slice()));
+        assert_eq!(library.recall("missing", &chain), None);
+        
+        let path = std::env::temp_dir().join("robot_arm_pose_library.ron");
+        library.save(&path).unwrap();
+        let loaded = PoseLibrary::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.recall("wave", &chain), Some(stored.as_slice()));
+    }
+}
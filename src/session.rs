@@ -0,0 +1,124 @@
+use std::{io, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    AccelerationDisplay, ControlBall, InnerBall, LimbAssets, LimbData, Segment, SegmentVelocityDisplay,
+    SyncTransform, VelocityDisplay, despawn_limb_entities, spawn_limb_entities,
+    templates::rebuild_chain,
+};
+
+pub const SESSION_FILE: &str = "session.ron";
+pub const SESSION_HISTORY_CAP: usize = 10_000;
+
+#[derive(Message, Default)]
+pub struct LoadSession;
+
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq)]
+pub struct Session {
+    pub joints: Vec<Vec3>,
+    pub velocity_history: Vec<Vec<f32>>,
+    pub acceleration_history: Vec<Vec<f32>>,
+    pub segment_velocity_history: Vec<Vec<f32>>,
+}
+
+fn capped(history: &[Vec<f32>]) -> Vec<Vec<f32>> {
+    history[history.len().saturating_sub(SESSION_HISTORY_CAP)..].to_vec()
+}
+
+impl Session {
+    pub fn capture(
+        joints: &[Vec3],
+        velocity_history: &[Vec<f32>],
+        acceleration_history: &[Vec<f32>],
+        segment_velocity_history: &[Vec<f32>]
+    ) -> Self {
+        Self {
+            joints: joints.to_vec(),
+            velocity_history: capped(velocity_history),
+            acceleration_history: capped(acceleration_history),
+            segment_velocity_history: capped(segment_velocity_history),
+        }
+    }
+    
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        ron::from_str(&contents).map_err(io::Error::other)
+    }
+    
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        std::fs::write(path, contents)
+    }
+}
+
+pub fn load_session(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chain: Query<&mut LimbData>,
+    mut query_velocity_display: Query<&mut VelocityDisplay>,
+    mut query_acceleration_display: Query<&mut AccelerationDisplay>,
+    mut query_segment_velocity_display: Query<&mut SegmentVelocityDisplay>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    let session = match Session::load(SESSION_FILE) {
+        Ok(session) => session,
+        Err(err) => {
+            warn!("Could not load {SESSION_FILE}: {err}");
+            return;
+        }
+    };
+    if session.joints.len() < 2 {
+        warn!("Could not load {SESSION_FILE}: expected at least 2 joints, found {}", session.joints.len());
+        return;
+    }
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    
+    if session.joints.len() == chain.0.joints.len() {
+        chain.0.joints = session.joints;
+        chain.0.sanitize();
+    } else {
+        let new_chain = rebuild_chain(&chain.0, session.joints);
+        despawn_limb_entities(&mut commands, &query_limb_entities);
+        spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &new_chain);
+        chain.0 = new_chain;
+    }
+    
+    if let Ok(mut velocity_display) = query_velocity_display.single_mut() {
+        velocity_display.0 = session.velocity_history;
+    }
+    if let Ok(mut acceleration_display) = query_acceleration_display.single_mut() {
+        acceleration_display.0 = session.acceleration_history;
+    }
+    if let Ok(mut segment_velocity_display) = query_segment_velocity_display.single_mut() {
+        segment_velocity_display.0 = session.segment_velocity_history;
+    }
+    ev_sync_transforms.write_default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn sessions_round_trip_their_history() {
+        let velocity_history: Vec<Vec<f32>> = (0..50).map(|i| vec![i as f32 * 0.25, -(i as f32) / 3.0]).collect();
+        let acceleration_history = vec![vec![1.5, -2.0], vec![0.125, 7.0]];
+        let session = Session::capture(&[Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)], &velocity_history, &acceleration_history, &[]);
+        
+        let path = std::env::temp_dir().join("robot_arm_session.ron");
+        session.save(&path).unwrap();
+        let loaded = Session::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, session);
+        assert_eq!(loaded.velocity_history.len(), 50);
+        assert_eq!(loaded.velocity_history[49], velocity_history[49]);
+        
+        let long_history = vec![vec![1.0]; SESSION_HISTORY_CAP + 5];
+        assert_eq!(Session::capture(&[], &long_history, &[], &[]).velocity_history.len(), SESSION_HISTORY_CAP);
+    }
+}
@@ -0,0 +1,103 @@
+use std::{
+    io::{BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+use bevy::{prelude::*, tasks::IoTaskPool};
+
+use robot_arm::ik::FabrikChain;
+
+use crate::LimbData;
+
+const TELEMETRY_ADDR: &str = "127.0.0.1:7878";
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        let listener = match TcpListener::bind(TELEMETRY_ADDR).and_then(|listener| {
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+        }) {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("Could not start telemetry on {TELEMETRY_ADDR}: {err}");
+                return;
+            }
+        };
+        info!("Serving telemetry at http://{TELEMETRY_ADDR}/state");
+        
+        app.insert_resource(TelemetryServer(listener))
+            .add_systems(Update, serve_telemetry);
+    }
+}
+
+#[derive(Resource)]
+struct TelemetryServer(TcpListener);
+
+pub fn state_json(chain: &FabrikChain) -> String {
+    let joints: Vec<[f32; 3]> = chain.joints.iter().map(|joint| joint.to_array()).collect();
+    serde_json::json!({
+        "joints": joints,
+        "angles": chain.angles,
+        "angular_velocities": chain.angular_velocities,
+        "segment_velocities": chain.segment_velocities,
+    })
+    .to_string()
+}
+
+fn respond(mut stream: TcpStream, body: String) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let (status, body) = match request_line.split_whitespace().collect::<Vec<_>>()[..] {
+        ["GET", "/state", ..] => ("200 OK", body),
+        ["GET", ..] => ("404 Not Found", "{\"error\":\"not found\"}".to_string()),
+        _ => ("405 Method Not Allowed", "{\"error\":\"method not allowed\"}".to_string()),
+    };
+    
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+fn serve_telemetry(server: Res<TelemetryServer>, query_chain: Query<&LimbData>) {
+    let Ok(chain) = query_chain.single() else { return; };
+    
+    while let Ok((stream, _)) = server.0.accept() {
+        let body = state_json(&chain.0);
+        IoTaskPool::get()
+            .spawn(async move {
+                if let Err(err) = respond(stream, body) {
+                    warn!("Telemetry request failed: {err}");
+                }
+            })
+            .detach();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use robot_arm::ik::MotionHueristics;
+    
+    #[test]
+    fn state_json_reports_the_pose() {
+        let mut chain = FabrikChain::new(vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0)], MotionHueristics::default());
+        chain.recalculate_angles();
+        let state: serde_json::Value = serde_json::from_str(&state_json(&chain)).unwrap();
+        
+        assert_eq!(state["joints"], serde_json::json!([[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]));
+        assert_eq!(state["angles"].as_array().unwrap().len(), 1);
+        let angle = state["angles"][0].as_f64().unwrap();
+        assert!((angle - std::f64::consts::FRAC_PI_2).abs() < 1e-6, "{angle}");
+        assert!(state["angular_velocities"].is_array());
+        assert!(state["segment_velocities"].is_array());
+    }
+}
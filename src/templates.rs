@@ -0,0 +1,147 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use robot_arm::{ik::FabrikChain, urdf};
+
+use crate::{
+    ControlBall, InnerBall, LimbAssets, LimbData, Segment, SyncTransform,
+    despawn_limb_entities, spawn_limb_entities,
+};
+
+pub const TEMPLATE_DIR: &str = "assets/chains";
+const TEMPLATE_EXTENSIONS: [&str; 4] = ["ron", "json", "bin", "urdf"];
+
+#[derive(Resource, Default)]
+pub struct ChainTemplates(pub Vec<PathBuf>);
+
+#[derive(Message)]
+pub struct LoadTemplate(pub PathBuf);
+
+pub fn discover_templates(paths: impl IntoIterator<Item = PathBuf>) -> Vec<PathBuf> {
+    let mut templates: Vec<PathBuf> = paths
+        .into_iter()
+        .filter(|path| {
+            template_extension(path).is_some_and(|ext| TEMPLATE_EXTENSIONS.contains(&ext.as_str()))
+        })
+        .collect();
+    templates.sort();
+    templates
+}
+
+fn template_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+pub fn template_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+pub fn load_template(path: impl AsRef<Path>) -> io::Result<Vec<Vec3>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)?;
+    let joints: Vec<Vec3> = match template_extension(path).as_deref() {
+        Some("json") => serde_json::from_str(&contents).map_err(io::Error::other)?,
+        Some("urdf") => urdf::parse_urdf(&contents).map_err(io::Error::other)?,
+        _ => ron::from_str(&contents).map_err(io::Error::other)?,
+    };
+    
+    if joints.len() < 2 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("expected at least 2 joints, found {}", joints.len())
+        ));
+    }
+    Ok(joints)
+}
+
+pub fn rebuild_chain(current: &FabrikChain, joints: Vec<Vec3>) -> FabrikChain {
+    let mut chain = FabrikChain::new(joints, current.motion_heuristics.clone());
+    chain.lock_ground = current.lock_ground;
+    chain.up_axis = current.up_axis;
+    chain.finalize();
+    if let (Some(limb), Some(current_limb)) = (chain.limb.as_mut(), current.limb.as_ref()) {
+        limb.lock_ground = current_limb.lock_ground;
+    }
+    chain.sanitize();
+    chain
+}
+
+pub fn discover_chain_templates(mut commands: Commands) {
+    let paths = match std::fs::read_dir(TEMPLATE_DIR) {
+        Ok(entries) => entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .collect(),
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Could not read {TEMPLATE_DIR}: {err}");
+            }
+            Vec::new()
+        }
+    };
+    commands.insert_resource(ChainTemplates(discover_templates(paths)));
+}
+
+pub fn load_chain_template(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query_chain: Query<&mut LimbData>,
+    query_limb_entities: Query<Entity, Or<(With<InnerBall>, With<ControlBall>, With<Segment>)>>,
+    limb_assets: Res<LimbAssets>,
+    mut ev_load_template: MessageReader<LoadTemplate>,
+    mut ev_sync_transforms: MessageWriter<SyncTransform>
+) {
+    let Some(LoadTemplate(path)) = ev_load_template.read().last() else { return; };
+    let Ok(mut chain) = query_chain.single_mut() else { return; };
+    
+    let loaded = match template_extension(path).as_deref() {
+        Some("bin") => FabrikChain::load_bincode(path),
+        _ => load_template(path).map(|joints| rebuild_chain(&chain.0, joints)),
+    };
+    let new_chain = match loaded {
+        Ok(new_chain) => new_chain,
+        Err(err) => {
+            warn!("Could not load template {}: {err}", path.display());
+            return;
+        }
+    };
+    
+    despawn_limb_entities(&mut commands, &query_limb_entities);
+    spawn_limb_entities(&mut commands, &mut meshes, &limb_assets, &new_chain);
+    chain.0 = new_chain;
+    ev_sync_transforms.write_default();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn discovery_keeps_sorted_chain_files() {
+        let listing = ["assets/chains/snake.RON", "assets/chains/readme.md", "assets/chains/arm.urdf", "assets/chains/notes", "assets/chains/crane.json"]
+            .map(PathBuf::from);
+        let templates = discover_templates(listing);
+        assert_eq!(templates, vec![
+            PathBuf::from("assets/chains/arm.urdf"),
+            PathBuf::from("assets/chains/crane.json"),
+            PathBuf::from("assets/chains/snake.RON"),
+        ]);
+        assert_eq!(template_name(&templates[0]), "arm");
+    }
+    
+    #[test]
+    fn uppercase_extensions_pick_their_parser() {
+        let path = std::env::temp_dir().join("robot_arm_template.JSON");
+        std::fs::write(&path, "[[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 2.0, 0.0]]").unwrap();
+        let joints = load_template(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(joints.unwrap(), vec![Vec3::ZERO, Vec3::X, Vec3::new(1.0, 2.0, 0.0)]);
+    }
+}
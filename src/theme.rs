@@ -0,0 +1,100 @@
+use std::{io, path::Path, str::FromStr};
+
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, egui::Visuals};
+
+use crate::{LimbAssets, UiState};
+
+pub const THEME_FILE: &str = "theme.txt";
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, strum::EnumIter, strum::Display, strum::EnumString)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        Self::from_str(contents.trim()).map_err(io::Error::other)
+    }
+    
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+    
+    pub fn clear_color(&self) -> Color {
+        match self {
+            Theme::Dark => Color::BLACK,
+            Theme::Light => Color::srgb(0.92, 0.92, 0.95),
+        }
+    }
+    
+    pub fn limb_color(&self) -> Color {
+        match self {
+            Theme::Dark => Color::WHITE,
+            Theme::Light => Color::srgb(0.3, 0.3, 0.35),
+        }
+    }
+    
+    pub fn visuals(&self) -> Visuals {
+        match self {
+            Theme::Dark => Visuals::dark(),
+            Theme::Light => Visuals::light(),
+        }
+    }
+}
+
+pub fn load_theme(mut ui_state: ResMut<UiState>) {
+    match Theme::load(THEME_FILE) {
+        Ok(theme) => ui_state.theme = theme,
+        Err(err) => {
+            if err.kind() != io::ErrorKind::NotFound {
+                warn!("Could not load {THEME_FILE}: {err}");
+            }
+        }
+    }
+}
+
+pub fn apply_theme(
+    mut context: EguiContexts,
+    mut clear_color: ResMut<ClearColor>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    limb_assets: Res<LimbAssets>,
+    ui_state: Res<UiState>,
+    mut applied: Local<Option<Theme>>
+) {
+    if *applied == Some(ui_state.theme) {
+        return;
+    }
+    let Ok(ctx) = context.ctx_mut() else { return; };
+    
+    let theme = ui_state.theme;
+    ctx.set_visuals(theme.visuals());
+    apply_scene_theme(theme, &mut clear_color, materials.get_mut(&limb_assets.material));
+    *applied = Some(theme);
+}
+
+fn apply_scene_theme(theme: Theme, clear_color: &mut ClearColor, limb_material: Option<&mut StandardMaterial>) {
+    clear_color.0 = theme.clear_color();
+    if let Some(material) = limb_material {
+        material.base_color = theme.limb_color();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn applying_a_theme_updates_the_clear_color() {
+        let mut clear_color = ClearColor(Theme::Dark.clear_color());
+        let mut material = StandardMaterial::default();
+        
+        apply_scene_theme(Theme::Light, &mut clear_color, Some(&mut material));
+        assert_eq!(clear_color.0, Theme::Light.clear_color());
+        assert_eq!(material.base_color, Theme::Light.limb_color());
+        assert_ne!(clear_color.0, Theme::Dark.clear_color());
+    }
+}
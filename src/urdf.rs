@@ -0,0 +1,216 @@
+use std::{fmt, io};
+
+use bevy_math::{EulerRot, Quat, Vec3};
+
+use crate::ik::{FabrikChain, MotionHueristics};
+
+#[derive(Debug)]
+pub enum UrdfError {
+    Io(io::Error),
+    Parse(String),
+    MissingBase,
+    Branching(String),
+    TooFewJoints(usize),
+}
+
+impl fmt::Display for UrdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UrdfError::Io(err) => write!(f, "{err}"),
+            UrdfError::Parse(message) => write!(f, "malformed URDF: {message}"),
+            UrdfError::MissingBase => f.write_str("no base link found"),
+            UrdfError::Branching(link) => write!(f, "link `{link}` has more than one child joint"),
+            UrdfError::TooFewJoints(count) => write!(f, "expected at least 2 joints, found {count}"),
+        }
+    }
+}
+
+impl std::error::Error for UrdfError {}
+
+impl From<io::Error> for UrdfError {
+    fn from(err: io::Error) -> Self {
+        UrdfError::Io(err)
+    }
+}
+
+struct UrdfJoint {
+    parent: String,
+    child: String,
+    xyz: Vec3,
+    rpy: Vec3,
+}
+
+fn strip_comments(xml: &str) -> String {
+    let mut stripped = String::with_capacity(xml.len());
+    let mut rest = xml;
+    while let Some(start) = rest.find("<!--") {
+        stripped.push_str(&rest[..start]);
+        rest = rest[start..].find("-->").map_or("", |end| &rest[start + end + 3..]);
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let open = format!("<{tag}");
+    let close = format!("</{tag}>");
+    let mut found = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find(&open) {
+        let after = &rest[start + open.len()..];
+        if !after.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+            rest = after;
+            continue;
+        }
+        let Some(head_end) = after.find('>') else { break; };
+        let end = if after[..head_end].ends_with('/') {
+            head_end + 1
+        } else {
+            after.find(&close).map_or(after.len(), |end| end + close.len())
+        };
+        found.push(&after[..end]);
+        rest = &after[end..];
+    }
+    found
+}
+
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let head = &element[..element.find('>').unwrap_or(element.len())];
+    let pattern = format!("{name}=");
+    let mut rest = head;
+    while let Some(start) = rest.find(&pattern) {
+        let preceded = rest[..start].ends_with(char::is_whitespace) || start == 0;
+        let value = &rest[start + pattern.len()..];
+        if let (true, Some(quote)) = (preceded, value.chars().next().filter(|c| *c == '"' || *c == '\'')) {
+            let value = &value[1..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+        rest = value;
+    }
+    None
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, UrdfError> {
+    let components = value
+        .split_whitespace()
+        .map(|component| component.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| UrdfError::Parse(format!("`{value}`: {err}")))?;
+    match components[..] {
+        [x, y, z] => Ok(Vec3::new(x, y, z)),
+        _ => Err(UrdfError::Parse(format!("expected 3 components, found `{value}`"))),
+    }
+}
+
+fn parse_joint(element: &str) -> Result<Option<UrdfJoint>, UrdfError> {
+    let link = |tag: &str| elements(element, tag).first().and_then(|link| attribute(link, "link")).map(str::to_owned);
+    let (Some(parent), Some(child)) = (link("parent"), link("child")) else {
+        return Ok(None);
+    };
+    let origin = elements(element, "origin").first().copied();
+    let vector = |name: &str| origin.and_then(|origin| attribute(origin, name)).map_or(Ok(Vec3::ZERO), parse_vec3);
+    Ok(Some(UrdfJoint { parent, child, xyz: vector("xyz")?, rpy: vector("rpy")? }))
+}
+
+fn to_y_up(v: Vec3) -> Vec3 {
+    Vec3::new(v.x, v.z, -v.y)
+}
+
+pub fn parse_urdf(xml: &str) -> Result<Vec<Vec3>, UrdfError> {
+    let xml = strip_comments(xml);
+    let robot = elements(&xml, "robot")
+        .first()
+        .copied()
+        .ok_or_else(|| UrdfError::Parse("missing <robot> element".to_string()))?;
+    let joints: Vec<UrdfJoint> = elements(robot, "joint")
+        .into_iter()
+        .filter_map(|element| parse_joint(element).transpose())
+        .collect::<Result<_, _>>()?;
+    
+    let base = joints
+        .iter()
+        .map(|joint| joint.parent.as_str())
+        .find(|link| joints.iter().all(|joint| joint.child != *link))
+        .ok_or(UrdfError::MissingBase)?;
+    
+    let mut positions = vec![Vec3::ZERO];
+    let (mut frame_pos, mut frame_rot) = (Vec3::ZERO, Quat::IDENTITY);
+    let mut link = base.to_string();
+    for _ in 0..joints.len() {
+        let mut children = joints.iter().filter(|joint| joint.parent == link);
+        let Some(joint) = children.next() else { break; };
+        if children.next().is_some() {
+            return Err(UrdfError::Branching(link));
+        }
+        frame_pos += frame_rot * joint.xyz;
+        frame_rot *= Quat::from_euler(EulerRot::ZYX, joint.rpy.z, joint.rpy.y, joint.rpy.x);
+        if positions.last().is_none_or(|last| last.distance(frame_pos) > f32::EPSILON) {
+            positions.push(frame_pos);
+        }
+        link = joint.child.clone();
+    }
+    
+    let positions: Vec<Vec3> = positions.into_iter().map(to_y_up).collect();
+    if positions.len() < 2 {
+        return Err(UrdfError::TooFewJoints(positions.len()));
+    }
+    Ok(positions)
+}
+
+impl FabrikChain {
+    pub fn from_urdf(path: &str) -> Result<Self, UrdfError> {
+        let contents = std::fs::read_to_string(path)?;
+        let joints = parse_urdf(&contents)?;
+        Ok(Self::new(joints, MotionHueristics::default()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const THREE_LINK_ARM: &str = r#"
+        <robot name="arm">
+            <link name="base"/>
+            <link name="upper"/>
+            <link name="fore"/>
+            <link name="hand"/>
+            <!-- <joint name="ignored"><parent link="hand"/><child link="finger"/></joint> -->
+            <joint name="shoulder" type="revolute">
+                <parent link="base"/>
+                <child link="upper"/>
+                <origin xyz="0 0 1"/>
+            </joint>
+            <joint name="elbow" type="revolute">
+                <parent link="upper"/>
+                <child link="fore"/>
+                <origin xyz="0 0 1" rpy="0 0 1.5707964"/>
+            </joint>
+            <joint name="wrist" type="revolute">
+                <parent link="fore"/>
+                <child link="hand"/>
+                <origin xyz="0.5 0 0"/>
+            </joint>
+        </robot>
+    "#;
+    
+    #[test]
+    fn three_link_arms_parse_into_a_chain() {
+        let joints = parse_urdf(THREE_LINK_ARM).unwrap();
+        assert_eq!(joints.len(), 4);
+        let expected = [Vec3::ZERO, Vec3::Y, Vec3::Y * 2.0, Vec3::new(0.0, 2.0, -0.5)];
+        for (joint, expected) in joints.iter().zip(expected) {
+            assert!(joint.distance(expected) < 1e-5, "{joints:?}");
+        }
+        
+        let path = std::env::temp_dir().join("robot_arm_three_link.urdf");
+        std::fs::write(&path, THREE_LINK_ARM).unwrap();
+        let chain = FabrikChain::from_urdf(path.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        for (length, expected) in chain.lengths.iter().zip([1.0, 1.0, 0.5]) {
+            assert!((length - expected).abs() < 1e-5, "{:?}", chain.lengths);
+        }
+        
+        assert!(matches!(parse_urdf("<robot></robot>"), Err(UrdfError::MissingBase)));
+    }
+}
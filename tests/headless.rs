@@ -0,0 +1,19 @@
+use bevy_math::Vec3;
+use robot_arm::ik::{FabrikChain, KinematicsMode, MotionHueristics, PoseDiscrepancy};
+
+#[test]
+fn solves_without_an_app() {
+    let joints = vec![Vec3::ZERO, Vec3::X, Vec3::X * 2.0, Vec3::X * 3.0];
+    let mut chain = FabrikChain::new(joints, MotionHueristics::default());
+    let target = Vec3::new(1.5, 1.5, 0.0);
+    chain.targets = vec![(3, target)];
+    
+    let report = chain.solve(50, PoseDiscrepancy::WithinTolerance, &mut KinematicsMode::default());
+    
+    assert!(report.reachable);
+    assert!(chain.get_ee().distance(target) < 1e-2, "end effector at {}", chain.get_ee());
+    assert_eq!(chain.joints[0], Vec3::ZERO);
+    for (pair, length) in chain.joints.windows(2).zip([1.0, 1.0, 1.0]) {
+        assert!((pair[0].distance(pair[1]) - length).abs() < 1e-3);
+    }
+}